@@ -1,7 +1,9 @@
 use fuzzyd::Fuzzyd;
 use clap::{Parser, Subcommand};
-use fuzzyd::sources::Source;
+use fuzzyd::sources::{Source, check_desktop_files};
+use fuzzyd::desktop_file::Severity;
 use fuzzyd::fuzzy::FuzzyItem;
+use fuzzyd::launcher::escape_field_codes;
 use std::fs;
 use std::path::PathBuf;
 
@@ -52,7 +54,7 @@ struct Cli {
         long,
         value_name = "COMMAND",
         help = "Launch a command directly without entering the interactive mode",
-        long_help = "Specify a command to launch directly, bypassing the interactive fuzzy search interface. This is useful for scripting or quick launches from other applications."
+        long_help = "Specify a command to launch directly, bypassing the interactive fuzzy search interface. The command is run with sh -c, so arguments can be quoted. This is useful for scripting or quick launches from other applications."
     )]
     exec: Option<String>,
 
@@ -80,23 +82,62 @@ struct Cli {
         long_help = "Enable dry run mode to print the command that would be run, but do not execute it. This is useful for testing and debugging."
     )]
     dry_run: bool,
+
+    /// Print the selection instead of acting on it
+    #[arg(
+        long,
+        help = "Print the selected item instead of launching or copying it",
+        long_help = "Print the command of the selected item, or the text it would copy, to stdout instead of launching or copying it. This is useful for picking items from scripts."
+    )]
+    print: bool,
+
+    /// Show hidden desktop entries
+    #[arg(
+        short,
+        long,
+        help = "Show desktop entries that would normally be hidden",
+        long_help = "Show desktop entries that are hidden by NoDisplay, Hidden, OnlyShowIn, NotShowIn or a missing TryExec executable, with the reason in their description."
+    )]
+    all: bool,
+
+    /// Open files or URLs with a chosen application
+    #[arg(
+        long,
+        value_name = "FILE_OR_URL",
+        num_args = 1..,
+        help = "Pick an application to open files or URLs with",
+        long_help = "Pick an application to open the given files or URLs with, from the desktop entries that can open them. Applications that list the MIME type of every target come first."
+    )]
+    open: Vec<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Write the default configuration file to the user's configuration directory
     Init,
+    /// Check desktop files for mistakes and tell why entries are skipped or hidden
+    CheckDesktop {
+        /// Desktop files to check, every listed desktop file if none are given
+        paths: Vec<PathBuf>,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
-    if let Some(Commands::Init) = cli.command {
-        write_default_config()?;
-        return Ok(());
+    match cli.command {
+        Some(Commands::Init) => {
+            write_default_config()?;
+            return Ok(());
+        }
+        Some(Commands::CheckDesktop { paths }) => check_desktop(&paths),
+        None => {}
     }
 
-    let sources = if cli.sources.is_empty() {
+    // Opening files only needs the desktop entries, which it loads itself
+    let sources = if !cli.open.is_empty() {
+        Vec::new()
+    } else if cli.sources.is_empty() {
         vec![Source::Desktop, Source::Path]
     } else {
         cli.sources
@@ -108,11 +149,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         cli.history_file
     };
 
-    let mut fuzzyd = Fuzzyd::new(sources, cli.debug, cli.config, history_file, cli.dry_run)?;
+    let mut fuzzyd = Fuzzyd::new(sources, cli.debug, cli.config, history_file, cli.dry_run, cli.print, cli.all)?;
 
-    if let Some(command) = cli.exec {
+    if !cli.open.is_empty() {
+        fuzzyd.open(&cli.open)?;
+    } else if let Some(command) = cli.exec {
         let item = FuzzyItem {
             display: command.clone(),
+            // The command line is run by the shell, so it can quote paths
+            // with spaces
+            argv: vec!["sh".to_string(), "-c".to_string(), escape_field_codes(&command)],
             exec: command,
             priority: 0,
             source_order: 0,
@@ -120,6 +166,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             source_path: String::new(),
             search_desc: false,
             icon: String::new(),
+            ..Default::default()
         };
         fuzzyd.launch(&item)?;
     } else {
@@ -129,6 +176,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+// Exits with status 1 if any file has errors
+fn check_desktop(paths: &[PathBuf]) -> ! {
+    let diagnostics = check_desktop_files(paths);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let count = |severity: Severity, noun: &str| {
+        match diagnostics.iter().filter(|diagnostic| diagnostic.severity == severity).count() {
+            1 => format!("1 {}", noun),
+            count => format!("{} {}s", count, noun),
+        }
+    };
+    println!("{}, {}", count(Severity::Error, "error"), count(Severity::Warning, "warning"));
+    let failed = diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error);
+    std::process::exit(if failed { 1 } else { 0 });
+}
+
 fn write_default_config() -> Result<(), Box<dyn std::error::Error>> {
     let config_dir = dirs::config_dir()
         .ok_or("Could not determine config directory")?;
@@ -0,0 +1,82 @@
+use crate::config::ClipboardConfig;
+use crate::error::FuzzydError;
+use base64::Engine;
+use std::io::{stdout, Write};
+use std::process::{Command, Stdio};
+
+// Tried in order when no paste command is configured
+const PASTE_COMMANDS: &[&str] = &[
+    "wl-paste --no-newline",
+    "xclip -selection clipboard -out",
+    "xsel --clipboard --output",
+];
+
+/// Copies text with the configured clipboard command, or with an OSC 52
+/// escape sequence that the terminal forwards to the system clipboard.
+/// The escape sequence is also used when the command fails, like wl-copy
+/// without a Wayland display.
+pub fn copy(text: &str, config: &ClipboardConfig) -> Result<(), FuzzydError> {
+    if let Some(command) = &config.command {
+        if copy_with_command(text, command).is_ok() {
+            return Ok(());
+        }
+    }
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut out = stdout();
+    write!(out, "\x1b]52;c;{}\x07", encoded)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Reads the clipboard with the configured paste command, or with the
+/// first of wl-paste, xclip and xsel that works.
+pub fn paste(config: &ClipboardConfig) -> Option<String> {
+    match &config.paste_command {
+        Some(command) => paste_with_command(command),
+        None => PASTE_COMMANDS.iter().find_map(|command| paste_with_command(command)),
+    }
+}
+
+fn paste_with_command(command: &str) -> Option<String> {
+    let mut parts = command.split_whitespace();
+    let output = Command::new(parts.next()?)
+        .args(parts)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn copy_with_command(text: &str, command: &str) -> Result<(), FuzzydError> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next()
+        .ok_or_else(|| FuzzydError::ClipboardError("Empty clipboard command".to_string()))?;
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| FuzzydError::ClipboardError(e.to_string()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()
+        .map_err(|e| FuzzydError::ClipboardError(e.to_string()))?;
+    if !status.success() {
+        return Err(FuzzydError::ClipboardError(format!("{} failed with {}", program, status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_commands_have_to_succeed() {
+        assert!(copy_with_command("text", "dd of=/dev/null status=none").is_ok());
+        assert!(copy_with_command("text", "grep -q no-match").is_err());
+        assert!(copy_with_command("text", "no-such-clipboard-command").is_err());
+        assert!(copy_with_command("text", " ").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use serde::Deserialize;
 use dirs;
+use std::path::PathBuf;
 
 #[derive(Deserialize, Default)]
 pub struct Config {
@@ -7,12 +8,37 @@ pub struct Config {
     pub debug: bool,
     pub history: HistoryConfig,
     pub systemd_run: SystemdRunConfig,
+    #[serde(default)]
+    pub desktop: DesktopConfig,
+    #[serde(default)]
+    pub terminal: TerminalConfig,
+    #[serde(default)]
+    pub kube: KubeConfig,
+    #[serde(default)]
+    pub places: PlacesConfig,
+    #[serde(default)]
+    pub notes: NotesConfig,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub dictionary: DictionaryConfig,
+    #[serde(default)]
+    pub sources: Vec<CommandSourceConfig>,
+    #[serde(default)]
+    pub snippets: SnippetsConfig,
 }
 
 #[derive(Deserialize, Default, Clone)]
 pub struct UIConfig {
     pub prompt: Option<String>,
     pub highlight_color: Option<String>,
+    /// List desktop actions under their application, expanded with Tab
+    #[serde(default)]
+    pub nested_actions: bool,
+    /// How to draw application icons: "auto", "kitty", "sixel" or "glyphs"
+    pub icons: Option<String>,
+    /// Icon theme to look icons up in, the GTK theme by default
+    pub icon_theme: Option<String>,
 }
 
 #[derive(Deserialize, Default, Clone)]
@@ -24,6 +50,76 @@ pub struct HistoryConfig {
 #[derive(Deserialize, Default, Clone)]
 pub struct SystemdRunConfig {
     pub parameters: Vec<String>,
+    /// `NAME=value` pairs set for applications that prefer the discrete GPU
+    pub discrete_gpu_env: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct DesktopConfig {
+    #[serde(default)]
+    pub show_hidden: bool,
+    /// Browse the applications.menu categories when the query is empty
+    #[serde(default)]
+    pub menu: bool,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct TerminalConfig {
+    pub command: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct KubeConfig {
+    #[serde(default)]
+    pub k9s: bool,
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct PlacesConfig {
+    pub file_manager: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct NotesConfig {
+    pub directory: Option<String>,
+    pub editor: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct ClipboardConfig {
+    pub command: Option<String>,
+    pub paste_command: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct DictionaryConfig {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    pub prefix: Option<String>,
+    pub max_results: Option<usize>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct CommandSourceConfig {
+    pub name: String,
+    pub command: String,
+    pub delimiter: Option<String>,
+    pub display: Option<String>,
+    pub exec: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub terminal: bool,
+    /// Seconds to wait for the command before leaving the source out
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct SnippetsConfig {
+    pub file: Option<String>,
+    pub directory: Option<String>,
 }
 
 impl Config {
@@ -48,4 +144,12 @@ impl Config {
         let config_str = std::fs::read_to_string(path)?;
         Ok(toml::from_str(&config_str)?)
     }
-}
\ No newline at end of file
+}
+
+/// Expands a leading `~` in a configured path to the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
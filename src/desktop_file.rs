@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Keys whose values are ;-separated lists, which keep their escapes until
+// they are split
+const LIST_KEYS: &[&str] = &["Actions", "Categories", "Implements", "Keywords", "MimeType", "NotShowIn", "OnlyShowIn"];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    /// Breaks the file or the entry
+    Error,
+    /// Likely a mistake, but the entry still works
+    Warning,
+    /// Explains how the entry is treated, like why it is hidden
+    Info,
+}
+
+/// A problem found in a desktop file, at a line counted from 1, or 0 when
+/// it is about the whole file.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub line: usize,
+    pub severity: Severity,
+    pub problem: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        if self.line == 0 {
+            write!(f, "{}: {}: {}", self.path.display(), severity, self.problem)
+        } else {
+            write!(f, "{}:{}: {}: {}", self.path.display(), self.line, severity, self.problem)
+        }
+    }
+}
+
+/// A `[Group]` of a desktop file. String values are unescaped, while list
+/// values and `Exec`, which has its own quoting rules, are kept as written.
+#[derive(Default)]
+pub struct Group {
+    pub name: String,
+    pub line: usize,
+    pub entries: HashMap<String, String>,
+    lines: HashMap<String, usize>,
+}
+
+impl Group {
+    /// The line a key was set on, or the group header's if it wasn't.
+    pub fn line_of(&self, key: &str) -> usize {
+        self.lines.get(key).copied().unwrap_or(self.line)
+    }
+}
+
+/// A desktop file parsed following the Desktop Entry spec. Lines that don't
+/// follow it are left out and reported.
+pub struct DesktopFile {
+    pub path: PathBuf,
+    pub groups: Vec<Group>,
+}
+
+impl DesktopFile {
+    pub fn read(path: &Path) -> io::Result<(DesktopFile, Vec<Diagnostic>)> {
+        Ok(DesktopFile::parse(path, &fs::read(path)?))
+    }
+
+    pub fn parse(path: &Path, contents: &[u8]) -> (DesktopFile, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut report = |line: usize, severity: Severity, problem: String| {
+            diagnostics.push(Diagnostic { path: path.to_path_buf(), line, severity, problem });
+        };
+        let mut groups: Vec<Group> = Vec::new();
+        let mut current: Option<usize> = None;
+
+        for (index, bytes) in contents.split(|byte| *byte == b'\n').enumerate() {
+            let number = index + 1;
+            let line = match std::str::from_utf8(bytes) {
+                Ok(line) => line.to_string(),
+                Err(_) => {
+                    report(number, Severity::Error, "line is not valid UTF-8".to_string());
+                    String::from_utf8_lossy(bytes).into_owned()
+                }
+            };
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                if name.is_empty() || name.contains(['[', ']']) || name.chars().any(char::is_control) {
+                    report(number, Severity::Error, format!("invalid group name [{}]", name));
+                    current = None;
+                    continue;
+                }
+                if groups.is_empty() && name != "Desktop Entry" {
+                    report(number, Severity::Error, format!("the first group is [{}], not [Desktop Entry]", name));
+                }
+                // Repeated groups are merged, as GLib does
+                current = Some(match groups.iter().position(|group| group.name == name) {
+                    Some(existing) => {
+                        report(number, Severity::Error, format!("group [{}] is repeated from line {}", name, groups[existing].line));
+                        existing
+                    }
+                    None => {
+                        groups.push(Group { name: name.to_string(), line: number, ..Default::default() });
+                        groups.len() - 1
+                    }
+                });
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    report(number, Severity::Error, format!("line is not a comment, group or key: {}", line));
+                    continue;
+                }
+            };
+            let group = match current {
+                Some(group) => &mut groups[group],
+                None => {
+                    if groups.is_empty() {
+                        report(number, Severity::Error, format!("key {} comes before the first group", key));
+                    }
+                    continue;
+                }
+            };
+            let base_key = match parse_key(key) {
+                Some(base_key) => base_key,
+                None => {
+                    report(number, Severity::Error, format!("invalid key name {}", key));
+                    continue;
+                }
+            };
+
+            let value = if base_key == "Exec" {
+                value.to_string()
+            } else if LIST_KEYS.contains(&base_key) {
+                if let Some(escape) = invalid_escape(value, &['s', 'n', 't', 'r', '\\', ';']) {
+                    report(number, Severity::Warning, format!("{} has an unknown escape sequence \\{}", key, escape));
+                }
+                value.to_string()
+            } else {
+                if let Some(escape) = invalid_escape(value, &['s', 'n', 't', 'r', '\\']) {
+                    report(number, Severity::Warning, format!("{} has an unknown escape sequence \\{}", key, escape));
+                }
+                unescape(value)
+            };
+
+            // The last value wins, as GLib does
+            if let Some(previous) = group.lines.insert(key.to_string(), number) {
+                report(number, Severity::Error, format!("key {} is repeated from line {}", key, previous));
+            }
+            group.entries.insert(key.to_string(), value);
+        }
+
+        if groups.is_empty() {
+            report(0, Severity::Error, "there is no [Desktop Entry] group".to_string());
+        }
+        (DesktopFile { path: path.to_path_buf(), groups }, diagnostics)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+}
+
+/// Splits a list value on the semicolons that aren't escaped and unescapes
+/// the parts.
+pub fn split_list(value: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => values.push(std::mem::take(&mut current)),
+            '\\' => {
+                current.push(c);
+                current.extend(chars.next());
+            }
+            c => current.push(c),
+        }
+    }
+    values.push(current);
+    values.iter()
+        .map(|value| unescape(value.trim()))
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Replaces the `\s`, `\n`, `\t`, `\r`, `\\` and `\;` escapes of a value.
+/// Other backslashes are kept.
+pub fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(';') => unescaped.push(';'),
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Returns the key without its locale, if the name is valid: A-Za-z0-9-,
+// followed by an optional [locale]
+fn parse_key(key: &str) -> Option<&str> {
+    let (base, locale) = match key.split_once('[') {
+        Some((base, locale)) => (base, Some(locale.strip_suffix(']')?)),
+        None => (key, None),
+    };
+    let valid_base = !base.is_empty() && base.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let valid_locale = locale.is_none_or(|locale| {
+        !locale.is_empty() && locale.chars().all(|c| c.is_ascii_alphanumeric() || "_@.-".contains(c))
+    });
+    (valid_base && valid_locale).then_some(base)
+}
+
+fn invalid_escape(value: &str, allowed: &[char]) -> Option<char> {
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some(escaped) if allowed.contains(&escaped) => {}
+                Some(escaped) => return Some(escaped),
+                None => {}
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &[u8]) -> (DesktopFile, Vec<(usize, Severity, String)>) {
+        let (file, diagnostics) = DesktopFile::parse(Path::new("test.desktop"), contents);
+        let diagnostics = diagnostics.into_iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.severity, diagnostic.problem))
+            .collect();
+        (file, diagnostics)
+    }
+
+    #[test]
+    fn groups_and_keys_are_read() {
+        let (file, diagnostics) = parse(b"# Comment\r\n[Desktop Entry]\r\nName = Files\r\nName[de]=Dateien\r\n\r\n[Desktop Action new]\nExec=files --new\n");
+        assert!(diagnostics.is_empty());
+        let entry = file.group("Desktop Entry").unwrap();
+        assert_eq!(entry.line, 2);
+        assert_eq!(entry.entries["Name"], "Files");
+        assert_eq!(entry.entries["Name[de]"], "Dateien");
+        assert_eq!(entry.line_of("Name[de]"), 4);
+        assert_eq!(entry.line_of("Exec"), 2);
+        assert_eq!(file.group("Desktop Action new").unwrap().entries["Exec"], "files --new");
+    }
+
+    #[test]
+    fn invalid_groups_are_reported() {
+        let (file, diagnostics) = parse(b"Name=Stray\n[Other]\nName=Other\n[]\nName=Lost\n[Other]\nComment=Again\n");
+        assert_eq!(diagnostics, [
+            (1, Severity::Error, "key Name comes before the first group".to_string()),
+            (2, Severity::Error, "the first group is [Other], not [Desktop Entry]".to_string()),
+            (4, Severity::Error, "invalid group name []".to_string()),
+            (6, Severity::Error, "group [Other] is repeated from line 2".to_string()),
+        ]);
+        // Keys of an invalid group are dropped, a repeated group is merged
+        assert_eq!(file.groups.len(), 1);
+        assert_eq!(file.groups[0].entries.len(), 2);
+
+        let (file, diagnostics) = parse(b"# Empty\n");
+        assert!(file.groups.is_empty());
+        assert_eq!(diagnostics, [(0, Severity::Error, "there is no [Desktop Entry] group".to_string())]);
+    }
+
+    #[test]
+    fn invalid_lines_and_keys_are_reported() {
+        let (file, diagnostics) = parse(b"[Desktop Entry]\nName=First\nName=Second\nnot a key\nBad_Key=1\nName[]=x\nComment=caf\xe9\n");
+        assert_eq!(diagnostics, [
+            (3, Severity::Error, "key Name is repeated from line 2".to_string()),
+            (4, Severity::Error, "line is not a comment, group or key: not a key".to_string()),
+            (5, Severity::Error, "invalid key name Bad_Key".to_string()),
+            (6, Severity::Error, "invalid key name Name[]".to_string()),
+            (7, Severity::Error, "line is not valid UTF-8".to_string()),
+        ]);
+        let entries = &file.groups[0].entries;
+        assert_eq!(entries["Name"], "Second");
+        assert_eq!(entries["Comment"], "caf\u{fffd}");
+    }
+
+    #[test]
+    fn values_are_unescaped_except_lists_and_exec() {
+        let (file, diagnostics) = parse(br"[Desktop Entry]
+Comment=a\sb\tc\\d\qe
+Keywords=one\;two;three;
+Exec=sh -c 'echo \\$HOME'
+MimeType=text/plain;\x;
+");
+        assert_eq!(diagnostics, [
+            (2, Severity::Warning, r"Comment has an unknown escape sequence \q".to_string()),
+            (5, Severity::Warning, r"MimeType has an unknown escape sequence \x".to_string()),
+        ]);
+        let entries = &file.groups[0].entries;
+        assert_eq!(entries["Comment"], "a b\tc\\d\\qe");
+        assert_eq!(entries["Keywords"], r"one\;two;three;");
+        assert_eq!(entries["Exec"], r"sh -c 'echo \\$HOME'");
+    }
+
+    #[test]
+    fn escapes_are_replaced() {
+        assert_eq!(unescape(r"a\sb\nc\td\re\\f\;g"), "a b\nc\td\re\\f;g");
+        assert_eq!(unescape(r"keep\x and\"), r"keep\x and\");
+    }
+
+    #[test]
+    fn lists_are_split_on_unescaped_semicolons() {
+        assert_eq!(split_list("GNOME;GTK;"), ["GNOME", "GTK"]);
+        assert_eq!(split_list(r"one\;two;three"), ["one;two", "three"]);
+        assert_eq!(split_list(r" spaced ; out\s;;"), ["spaced", "out "]);
+        assert_eq!(split_list(r"back\\;slash"), ["back\\", "slash"]);
+        assert!(split_list("").is_empty());
+    }
+
+    #[test]
+    fn keys_may_have_a_locale() {
+        assert_eq!(parse_key("Name"), Some("Name"));
+        assert_eq!(parse_key("X-GNOME-Autostart"), Some("X-GNOME-Autostart"));
+        assert_eq!(parse_key("Name[sr_RS@latin]"), Some("Name"));
+        assert_eq!(parse_key("Name[de_DE.UTF-8]"), Some("Name"));
+        assert_eq!(parse_key("Name[]"), None);
+        assert_eq!(parse_key("Name[de"), None);
+        assert_eq!(parse_key("[de]"), None);
+        assert_eq!(parse_key("Bad Key"), None);
+    }
+}
@@ -6,6 +6,8 @@ pub enum FuzzydError {
     IOError(#[from] std::io::Error),
     #[error("Failed to launch command: {0}")]
     LaunchError(String),
+    #[error("Failed to copy to the clipboard: {0}")]
+    ClipboardError(String),
     #[error("User interrupted the operation")]
     UserInterrupt,
 }
\ No newline at end of file
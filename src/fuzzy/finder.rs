@@ -2,22 +2,75 @@ use super::history::History;
 use std::path::PathBuf;
 use std::collections::HashMap;
 use super::scorer::match_and_score;
+use super::provider::QueryProvider;
 
 pub struct FuzzyFinder {
     items: Vec<FuzzyItem>,
     history: History,
+    providers: Vec<Box<dyn QueryProvider>>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct FuzzyItem {
     pub display: String,
     pub exec: String,
+    /// Arguments to launch with instead of splitting `exec`, in the Desktop
+    /// Entry Exec form that keeps `%%` and the `%f`/`%F`/`%u`/`%U` codes
+    pub argv: Vec<String>,
     pub priority: u8,
     pub source_order: usize,
     pub description: String,
     pub source_path: String,
     pub search_desc: bool,
     pub icon: String,
+    /// Icon theme name from a desktop entry
+    pub icon_name: Option<String>,
+    pub keywords: Vec<String>,
+    /// A generic name like "Web Browser"
+    pub generic_name: Option<String>,
+    /// Menu categories like "Graphics"
+    pub categories: Vec<String>,
+    pub action: ItemAction,
+    /// Identifies the item when deduplicating, instead of `exec`
+    pub id: Option<String>,
+    /// MIME types of the files and URLs the item can open
+    pub mime_types: Vec<String>,
+    /// The file or URL the item stands for, for opening it with another
+    /// application
+    pub target: Option<String>,
+    /// Directory to launch the item in
+    pub working_dir: Option<String>,
+    /// Whether to launch the item on the discrete GPU
+    pub prefers_non_default_gpu: bool,
+    /// Desktop actions listed under the item when they are nested
+    pub actions: Vec<FuzzyItem>,
+}
+
+/// What happens when an item is selected.
+#[derive(Clone, Default, PartialEq)]
+pub enum ItemAction {
+    /// Launch `exec` through the launcher
+    #[default]
+    Launch,
+    /// Copy the text to the clipboard
+    Copy(String),
+    /// Fill in the snippet's placeholders, then copy the result
+    Snippet(String),
+    /// Pick an application to open the file or URL with
+    OpenWith(String),
+    /// Open the file or URL with its default application
+    OpenDefault(String),
+    /// Run the argv directly rather than in a new unit, for commands that
+    /// manage the units fuzzyd started
+    Run,
+    /// Show the submenu at this index of the menu being browsed
+    Browse(usize),
+}
+
+impl FuzzyItem {
+    fn dedup_key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.exec)
+    }
 }
 
 impl FuzzyFinder {
@@ -25,26 +78,16 @@ impl FuzzyFinder {
         FuzzyFinder {
             items: Vec::new(),
             history: History::new(history_file),
+            providers: Vec::new(),
         }
     }
 
     pub fn add_items(&mut self, new_items: Vec<FuzzyItem>) {
         let mut unique_items: HashMap<String, FuzzyItem> = HashMap::new();
 
-        // First, add existing items to the HashMap
-        for item in self.items.drain(..) {
-            unique_items.entry(item.exec.clone())
-                .and_modify(|existing| {
-                    if item.source_order < existing.source_order {
-                        *existing = item.clone();
-                    }
-                })
-                .or_insert(item);
-        }
-
-        // Then, add new items, respecting source order
-        for item in new_items {
-            unique_items.entry(item.exec.clone())
+        // Existing items first, then new ones, respecting source order
+        for item in self.items.drain(..).chain(new_items) {
+            unique_items.entry(item.dedup_key().to_string())
                 .and_modify(|existing| {
                     if item.source_order < existing.source_order {
                         *existing = item.clone();
@@ -56,12 +99,22 @@ impl FuzzyFinder {
         self.items = unique_items.into_values().collect();
     }
 
+    /// Removes all items and providers, keeping the history.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.providers.clear();
+    }
+
+    pub fn add_providers(&mut self, providers: Vec<Box<dyn QueryProvider>>) {
+        self.providers.extend(providers);
+    }
+
     pub fn find(&self, query: &str) -> Vec<(f64, &FuzzyItem)> {
         let query = query.to_lowercase();
         
         // If the query is empty, return all items sorted by priority and history
         if query.is_empty() {
-            return self.items
+            let mut items: Vec<_> = self.items
                 .iter()
                 .map(|item| {
                     let history_boost = self.history.get_count(&item.exec) as f64 * 10.0;
@@ -69,6 +122,8 @@ impl FuzzyFinder {
                     (priority_score + history_boost, item)
                 })
                 .collect();
+            items.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+            return items;
         }
 
         // Rest of the method remains the same for non-empty queries
@@ -78,21 +133,34 @@ impl FuzzyFinder {
                 let mut total_score = 0.0;
 
                 // Always score the display field
-                total_score += match_and_score(&item.display, &query).unwrap_or(0.0) as f64;
+                total_score += match_and_score(&item.display, &query).unwrap_or(0.0);
 
                 // Score exec only if it's different from display
                 if item.exec != item.display {
-                    total_score += (match_and_score(&item.exec, &query).unwrap_or(0.0).max(0.0) as f64) * 0.8;
+                    total_score += match_and_score(&item.exec, &query).unwrap_or(0.0).max(0.0) * 0.8;
                 }
 
                 // Score description only if search_desc is true
                 if item.search_desc {
-                    total_score += (match_and_score(&item.description, &query).unwrap_or(0.0).max(0.0) as f64) * 0.6;
+                    total_score += match_and_score(&item.description, &query).unwrap_or(0.0).max(0.0) * 0.6;
+                }
+
+                // Score the best matching keyword
+                total_score += item.keywords.iter()
+                    .filter_map(|keyword| match_and_score(keyword, &query))
+                    .fold(0.0, f64::max) * 0.7;
+
+                // Score the generic name and the best matching category
+                if let Some(generic_name) = &item.generic_name {
+                    total_score += match_and_score(generic_name, &query).unwrap_or(0.0).max(0.0) * 0.75;
                 }
+                total_score += item.categories.iter()
+                    .filter_map(|category| match_and_score(category, &query))
+                    .fold(0.0, f64::max) * 0.5;
 
                 // Score source_path only if it's different from exec
                 if item.source_path != item.exec {
-                    total_score += (match_and_score(&item.source_path, &query).unwrap_or(0.0).max(0.0) as f64) * 0.4;
+                    total_score += match_and_score(&item.source_path, &query).unwrap_or(0.0).max(0.0) * 0.4;
                 }
 
                 if total_score > 0.0 {
@@ -107,6 +175,19 @@ impl FuzzyFinder {
         matches
     }
 
+    /// Returns the items produced by query providers for this query. Fallback
+    /// providers only contribute when `has_matches` is false.
+    pub fn provide(&self, query: &str, has_matches: bool) -> Vec<FuzzyItem> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        self.providers
+            .iter()
+            .filter(|provider| !has_matches || !provider.is_fallback())
+            .flat_map(|provider| provider.provide(query))
+            .collect()
+    }
+
     pub fn record_usage(&mut self, command: &str) {
         self.history.record_usage(command);
     }
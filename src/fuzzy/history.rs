@@ -18,7 +18,13 @@ impl History {
         let mut entries = HashMap::new();
         if let Ok(file) = File::open(file_path) {
             let reader = BufReader::new(file);
-            for line in reader.lines().filter_map(Result::ok) {
+            for line in reader.lines() {
+                // Lines that aren't valid UTF-8 are skipped, other read errors end the file
+                let line = match line {
+                    Ok(line) => line,
+                    Err(err) if err.kind() == std::io::ErrorKind::InvalidData => continue,
+                    Err(_) => break,
+                };
                 let parts: Vec<&str> = line.split('\t').collect();
                 if parts.len() == 2 {
                     if let Ok(count) = parts[1].parse::<u32>() {
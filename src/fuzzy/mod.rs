@@ -1,6 +1,8 @@
 mod finder;
 mod scorer;
 mod history;
+mod provider;
 
-pub use finder::{FuzzyFinder, FuzzyItem};
-pub use scorer::match_and_score;
\ No newline at end of file
+pub use finder::{FuzzyFinder, FuzzyItem, ItemAction};
+pub use scorer::match_and_score;
+pub use provider::QueryProvider;
\ No newline at end of file
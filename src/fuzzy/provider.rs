@@ -0,0 +1,12 @@
+use super::finder::FuzzyItem;
+
+/// Produces items from the query itself rather than from a loaded list,
+/// e.g. a "create note" entry or a computed result.
+pub trait QueryProvider: Send + Sync {
+    fn provide(&self, query: &str) -> Vec<FuzzyItem>;
+
+    /// Fallback providers are only consulted when nothing else matches.
+    fn is_fallback(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use xdg::BaseDirectories;
+
+/// How the icons of a theme directory match sizes.
+#[derive(Clone, Copy, PartialEq)]
+enum DirType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// A subdirectory listed in an index.theme.
+struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    dir_type: DirType,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+struct Theme {
+    /// The theme's directory in each base directory that has one
+    roots: Vec<PathBuf>,
+    dirs: Vec<ThemeDir>,
+}
+
+/// Finds icon files by name following the Icon Theme spec: the theme and
+/// the themes it inherits from are searched for the closest size, then
+/// hicolor, then the base directories themselves.
+pub struct IconLookup {
+    base_dirs: Vec<PathBuf>,
+    themes: Vec<Theme>,
+    extensions: Vec<String>,
+    cache: HashMap<(String, u32, u32), Option<PathBuf>>,
+}
+
+impl ThemeDir {
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.dir_type {
+            DirType::Fixed => self.size == size,
+            DirType::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirType::Threshold => (self.size.saturating_sub(self.threshold)..=self.size + self.threshold).contains(&size),
+        }
+    }
+
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (min, max) = match self.dir_type {
+            DirType::Fixed => (self.size, self.size),
+            DirType::Scalable => (self.min_size, self.max_size),
+            DirType::Threshold => (self.size.saturating_sub(self.threshold), self.size + self.threshold),
+        };
+        if wanted < min * self.scale {
+            min * self.scale - wanted
+        } else {
+            wanted.saturating_sub(max * self.scale)
+        }
+    }
+}
+
+impl IconLookup {
+    /// Loads the named theme and the ones it inherits from. Only files with
+    /// one of the extensions are returned, in order of preference.
+    pub fn new(theme: &str, extensions: &[&str]) -> Self {
+        let mut base_dirs = Vec::new();
+        if let Some(home) = dirs::home_dir() {
+            base_dirs.push(home.join(".icons"));
+        }
+        if let Ok(xdg_dirs) = BaseDirectories::new() {
+            base_dirs.push(xdg_dirs.get_data_home().join("icons"));
+            base_dirs.extend(xdg_dirs.get_data_dirs().into_iter().map(|dir| dir.join("icons")));
+        }
+        base_dirs.push(PathBuf::from("/usr/share/pixmaps"));
+
+        // Themes are searched depth first through Inherits, hicolor last
+        let mut themes = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![theme.to_string()];
+        while let Some(name) = pending.pop() {
+            if name == "hicolor" || !seen.insert(name.clone()) {
+                continue;
+            }
+            if let Some((theme, inherits)) = load_theme(&base_dirs, &name) {
+                themes.push(theme);
+                pending.extend(inherits.into_iter().rev());
+            }
+        }
+        themes.extend(load_theme(&base_dirs, "hicolor").map(|(theme, _)| theme));
+
+        IconLookup {
+            base_dirs,
+            themes,
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Finds the file for an icon name or absolute path at a size in
+    /// pixels and a scale.
+    pub fn find(&mut self, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let key = (icon.to_string(), size, scale);
+        if let Some(path) = self.cache.get(&key) {
+            return path.clone();
+        }
+        let path = if Path::new(icon).is_absolute() {
+            Some(PathBuf::from(icon)).filter(|path| path.is_file() && self.has_extension(path))
+        } else {
+            self.themes.iter()
+                .find_map(|theme| self.find_in_theme(theme, icon, size, scale))
+                .or_else(|| self.find_fallback(icon))
+        };
+        self.cache.insert(key, path.clone());
+        path
+    }
+
+    fn find_in_theme(&self, theme: &Theme, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        if let Some(path) = theme.dirs.iter()
+            .filter(|dir| dir.matches_size(size, scale))
+            .flat_map(|dir| self.candidates(theme, dir, icon))
+            .find(|path| path.is_file())
+        {
+            return Some(path);
+        }
+
+        // Otherwise the closest size wins
+        theme.dirs.iter()
+            .flat_map(|dir| self.candidates(theme, dir, icon).map(move |path| (dir.size_distance(size, scale), path)))
+            .filter(|(_, path)| path.is_file())
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, path)| path)
+    }
+
+    fn candidates<'a>(&'a self, theme: &'a Theme, dir: &'a ThemeDir, icon: &'a str) -> impl Iterator<Item = PathBuf> + 'a {
+        theme.roots.iter().flat_map(move |root| {
+            self.extensions.iter().map(move |ext| root.join(&dir.path).join(format!("{}.{}", icon, ext)))
+        })
+    }
+
+    fn find_fallback(&self, icon: &str) -> Option<PathBuf> {
+        self.base_dirs.iter()
+            .flat_map(|dir| self.extensions.iter().map(move |ext| dir.join(format!("{}.{}", icon, ext))))
+            .find(|path| path.is_file())
+    }
+
+    fn has_extension(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|ext| self.extensions.iter().any(|allowed| ext == allowed.as_str()))
+    }
+}
+
+/// The icon theme set for GTK, which most desktops share.
+pub fn default_theme() -> Option<String> {
+    let settings = dirs::config_dir()?.join("gtk-3.0").join("settings.ini");
+    let contents = fs::read_to_string(settings).ok()?;
+    contents.lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == "gtk-icon-theme-name")
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+}
+
+// Returns the theme and the names of the themes it inherits from
+fn load_theme(base_dirs: &[PathBuf], name: &str) -> Option<(Theme, Vec<String>)> {
+    let roots: Vec<PathBuf> = base_dirs.iter()
+        .map(|dir| dir.join(name))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    let index = roots.iter().find_map(|root| fs::read_to_string(root.join("index.theme")).ok())?;
+    let groups = parse_index(&index);
+    let theme_group = groups.get("Icon Theme")?;
+
+    let list = |key: &str| -> Vec<String> {
+        theme_group.get(key)
+            .map(|value| value.split(',').map(str::trim).filter(|dir| !dir.is_empty()).map(String::from).collect())
+            .unwrap_or_default()
+    };
+    let mut dir_names = list("Directories");
+    dir_names.extend(list("ScaledDirectories"));
+
+    let dirs = dir_names.into_iter()
+        .filter_map(|path| {
+            let group = groups.get(&path)?;
+            let number = |key: &str| group.get(key).and_then(|value| value.parse::<u32>().ok());
+            let size = number("Size")?;
+            let dir_type = match group.get("Type").map(String::as_str) {
+                Some("Fixed") => DirType::Fixed,
+                Some("Scalable") => DirType::Scalable,
+                _ => DirType::Threshold,
+            };
+            Some(ThemeDir {
+                size,
+                scale: number("Scale").unwrap_or(1),
+                dir_type,
+                min_size: number("MinSize").unwrap_or(size),
+                max_size: number("MaxSize").unwrap_or(size),
+                threshold: number("Threshold").unwrap_or(2),
+                path,
+            })
+        })
+        .collect();
+
+    Some((Theme { roots, dirs }, list("Inherits")))
+}
+
+fn parse_index(contents: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut groups: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = String::new();
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') && line.ends_with(']') {
+            current = line[1..line.len() - 1].to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            groups.entry(current.clone()).or_default().insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    groups
+}
@@ -0,0 +1,55 @@
+use which::which;
+use super::escape_field_codes;
+
+/// Resolves the executable at the start of a command line to an absolute
+/// path, keeping any arguments that follow it.
+pub fn resolve_command(command: &str) -> Option<String> {
+    resolve_argv(command).map(|argv| argv.join(" "))
+}
+
+/// Splits a configured command line on whitespace, resolving the
+/// executable to an absolute path.
+pub fn resolve_argv(command: &str) -> Option<Vec<String>> {
+    let mut parts = command.split_whitespace();
+    let executable = which(parts.next()?).ok()?;
+    let mut argv = vec![executable.to_str()?.to_string()];
+    argv.extend(parts.map(String::from));
+    Some(argv)
+}
+
+/// Joins an argv into a command line for display, quoting the arguments
+/// that a shell would split or expand.
+pub fn quote_argv(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| {
+            let plain = !arg.is_empty() && arg.chars()
+                .all(|c| c.is_alphanumeric() || "-_./=:@%+,".contains(c));
+            if plain {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Builds an argv from a configured command line and literal arguments
+/// that may contain spaces, like file paths.
+pub fn command_argv(command: &str, args: &[&str]) -> Vec<String> {
+    command.split_whitespace()
+        .chain(args.iter().copied())
+        .map(escape_field_codes)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_are_quoted_for_the_shell() {
+        let argv: Vec<String> = ["sh", "-c", "echo \"$1\" && it's", "sh", "", "a=b"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(quote_argv(&argv), "sh -c 'echo \"$1\" && it'\\''s' sh '' a=b");
+    }
+}
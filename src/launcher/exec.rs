@@ -0,0 +1,66 @@
+use crate::uri::{path_to_uri, uri_to_path};
+use std::path::Path;
+
+/// Substitutes the file and URL field codes left in a desktop entry style
+/// argv: `%f` and `%u` take the first target, `%F` and `%U` all of them,
+/// and `%%` is a literal percent sign. Arguments that consist of a field
+/// code are dropped when there is nothing to put in their place.
+pub fn expand_field_codes(argv: &[String], targets: &[String]) -> Vec<String> {
+    let files: Vec<String> = targets.iter().filter_map(|target| as_file(target)).collect();
+    let urls: Vec<String> = targets.iter().map(|target| as_url(target)).collect();
+
+    let mut expanded = Vec::new();
+    for arg in argv {
+        match arg.as_str() {
+            "%F" => expanded.extend(files.iter().cloned()),
+            "%U" => expanded.extend(urls.iter().cloned()),
+            "%f" | "%u" => {
+                let list = if arg == "%f" { &files } else { &urls };
+                expanded.extend(list.first().cloned());
+            }
+            _ => {
+                let mut result = String::new();
+                let mut chars = arg.chars();
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        result.push(c);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('%') => result.push('%'),
+                        Some('f') => result.push_str(files.first().map_or("", String::as_str)),
+                        Some('u') => result.push_str(urls.first().map_or("", String::as_str)),
+                        _ => {}
+                    }
+                }
+                expanded.push(result);
+            }
+        }
+    }
+    expanded
+}
+
+/// Escapes a literal argument for an argv that goes through
+/// `expand_field_codes`.
+pub fn escape_field_codes(arg: &str) -> String {
+    arg.replace('%', "%%")
+}
+
+// %f and %F only take local files, so other URLs are left out
+fn as_file(target: &str) -> Option<String> {
+    if target.contains("://") {
+        return uri_to_path(target).map(|path| path.to_string_lossy().to_string());
+    }
+    Some(target.to_string())
+}
+
+fn as_url(target: &str) -> String {
+    if target.contains("://") {
+        return target.to_string();
+    }
+    let path = Path::new(target);
+    match path.canonicalize() {
+        Ok(path) => path_to_uri(&path),
+        Err(_) => path_to_uri(path),
+    }
+}
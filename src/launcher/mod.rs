@@ -1,3 +1,10 @@
 mod systemd;
+mod terminal;
+mod command;
+mod exec;
 
-pub use systemd::SystemdLauncher;
\ No newline at end of file
+pub use systemd::SystemdLauncher;
+pub use terminal::{terminal_command, terminal_argv, in_terminal};
+pub use command::{resolve_command, resolve_argv, command_argv, quote_argv};
+
+pub use exec::{expand_field_codes, escape_field_codes};
\ No newline at end of file
@@ -1,15 +1,21 @@
 use crate::fuzzy::FuzzyItem;
+use std::os::unix::process::CommandExt;
 use std::process::Command;
 use crate::error::FuzzydError;
 use rand::Rng;
 use crate::config::SystemdRunConfig;
 use std::env;
 use regex::Regex;
-use std::fs;
+use which::which;
+use super::expand_field_codes;
+
+// Mesa's switch to the discrete GPU, used unless others are configured
+const DEFAULT_DISCRETE_GPU_ENV: &[&str] = &["DRI_PRIME=1"];
 
 pub struct SystemdLauncher {
     dry_run: bool,
     parameters: Vec<String>,
+    discrete_gpu_env: Vec<String>,
 }
 
 impl SystemdLauncher {
@@ -17,9 +23,12 @@ impl SystemdLauncher {
         let parameters = config.parameters.iter()
             .map(|param| Self::expand_env_vars(param))
             .collect();
+        let discrete_gpu_env = config.discrete_gpu_env.clone()
+            .unwrap_or_else(|| DEFAULT_DISCRETE_GPU_ENV.iter().map(|env| env.to_string()).collect());
         SystemdLauncher { 
             dry_run, 
             parameters,
+            discrete_gpu_env,
         }
     }
 
@@ -31,7 +40,42 @@ impl SystemdLauncher {
     }
 
     pub fn launch(&self, item: &FuzzyItem) -> Result<(), FuzzydError> {
-        let command = &item.exec;
+        self.launch_with(item, &[])
+    }
+
+    /// Launches the item with files or URLs substituted into its argv.
+    pub fn launch_with(&self, item: &FuzzyItem, targets: &[String]) -> Result<(), FuzzydError> {
+        let mut cmd = self.command(item, targets)?;
+        if self.dry_run {
+            println!("Dry run: {:?}", cmd);
+        } else {
+            let mut child = cmd.spawn()
+                .map_err(|e| FuzzydError::LaunchError(e.to_string()))?;
+            child.wait()
+                .map_err(|e| FuzzydError::LaunchError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Runs the item's argv as it is, without systemd-run. It gets its own
+    /// process group, so it isn't hung up along with fuzzyd's terminal.
+    pub fn spawn(&self, item: &FuzzyItem) -> Result<(), FuzzydError> {
+        let argv = expand_field_codes(&item.argv, &[]);
+        let (program, args) = argv.split_first()
+            .ok_or_else(|| FuzzydError::LaunchError(format!("Nothing to run for {}", item.display)))?;
+        let mut cmd = Command::new(program);
+        cmd.args(args).process_group(0);
+        if self.dry_run {
+            println!("Dry run: {:?}", cmd);
+            return Ok(());
+        }
+        cmd.spawn()
+            .map(|_| ())
+            .map_err(|e| FuzzydError::LaunchError(format!("{}: {}", program, e)))
+    }
+
+    /// Builds the systemd-run command that launches the item in its own unit.
+    fn command(&self, item: &FuzzyItem, targets: &[String]) -> Result<Command, FuzzydError> {
         let random_number: u32 = rand::thread_rng().gen_range(100_000..=999_999);
         let unit_cmd_name = format!("app-fuzzyd-{}-{:06}", 
             item.display
@@ -47,37 +91,101 @@ impl SystemdLauncher {
         }
         cmd.arg("--unit")
             .arg(&unit_cmd_name);
-
-        // Split the command into parts manually
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        
-        // Find the executable by iteratively checking from the back
-        let mut executable = None;
-        for i in (1..=parts.len()).rev() {
-            let potential_executable = parts[..i].join(" ");
-            if fs::metadata(&potential_executable).is_ok() {
-                executable = Some((potential_executable, &parts[i..]));
-                break;
-            }
+        if let Some(working_dir) = &item.working_dir {
+            cmd.arg(format!("--working-directory={}", working_dir));
         }
-
-        if let Some((executable, args)) = executable {
-            cmd.arg(executable);
-            for arg in args {
-                cmd.arg(arg);
+        if item.prefers_non_default_gpu {
+            for env in &self.discrete_gpu_env {
+                cmd.arg(format!("--setenv={}", env));
             }
-        } else {
-            return Err(FuzzydError::LaunchError("Executable not found".to_string()));
         }
 
-        if self.dry_run {
-            println!("Dry run: {:?}", cmd);
-        } else {
-            let mut child = cmd.spawn()
-                .map_err(|e| FuzzydError::LaunchError(e.to_string()))?;
-            child.wait()
-                .map_err(|e| FuzzydError::LaunchError(e.to_string()))?;
+        // Exec=%U alone expands to nothing without targets
+        let argv = expand_field_codes(&item.argv, targets);
+        let (program, args) = argv.split_first()
+            .ok_or_else(|| FuzzydError::LaunchError(format!("Nothing to run for {}", item.display)))?;
+        let executable = which(program)
+            .map_err(|_| FuzzydError::LaunchError(format!("Executable not found: {}", program)))?;
+        cmd.arg(executable).args(args);
+        Ok(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launcher(parameters: &[&str], discrete_gpu_env: Option<&[&str]>) -> SystemdLauncher {
+        let config = SystemdRunConfig {
+            parameters: parameters.iter().map(|param| param.to_string()).collect(),
+            discrete_gpu_env: discrete_gpu_env.map(|env| env.iter().map(|var| var.to_string()).collect()),
+        };
+        SystemdLauncher::new(true, &config)
+    }
+
+    // The arguments after the randomly named unit
+    fn args(launcher: &SystemdLauncher, item: &FuzzyItem, targets: &[String]) -> Vec<String> {
+        let cmd = launcher.command(item, targets).unwrap();
+        assert_eq!(cmd.get_program(), "/usr/bin/systemd-run");
+        let args: Vec<String> = cmd.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+        let unit = args.iter().position(|arg| arg == "--unit").unwrap();
+        assert!(args[unit + 1].starts_with("app-fuzzyd-"));
+        args[..unit].iter().chain(&args[unit + 2..]).cloned().collect()
+    }
+
+    fn item(argv: &[&str]) -> FuzzyItem {
+        FuzzyItem {
+            display: "Shell".to_string(),
+            argv: argv.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
         }
-        Ok(())
+    }
+
+    #[test]
+    fn working_directory_and_discrete_gpu_env() {
+        let item = FuzzyItem {
+            working_dir: Some("/srv/my project".to_string()),
+            prefers_non_default_gpu: true,
+            ..item(&["/bin/sh", "-c", "true"])
+        };
+        let launcher = launcher(&["--user", "--scope"], Some(&["__NV_PRIME_RENDER_OFFLOAD=1", "__GLX_VENDOR_LIBRARY_NAME=nvidia"]));
+        assert_eq!(args(&launcher, &item, &[]), [
+            "--user",
+            "--scope",
+            "--working-directory=/srv/my project",
+            "--setenv=__NV_PRIME_RENDER_OFFLOAD=1",
+            "--setenv=__GLX_VENDOR_LIBRARY_NAME=nvidia",
+            "/bin/sh",
+            "-c",
+            "true",
+        ]);
+    }
+
+    #[test]
+    fn discrete_gpu_env_defaults_to_dri_prime() {
+        let item = FuzzyItem { prefers_non_default_gpu: true, ..item(&["/bin/sh"]) };
+        assert_eq!(args(&launcher(&[], None), &item, &[]), ["--setenv=DRI_PRIME=1", "/bin/sh"]);
+        let item = FuzzyItem { prefers_non_default_gpu: false, ..item };
+        assert_eq!(args(&launcher(&[], None), &item, &[]), ["/bin/sh"]);
+    }
+
+    #[test]
+    fn targets_fill_field_codes() {
+        let targets = vec!["/tmp/a file.txt".to_string(), "https://example.com".to_string()];
+        assert_eq!(
+            args(&launcher(&[], None), &item(&["/bin/sh", "%U"]), &targets),
+            ["/bin/sh", "file:///tmp/a%20file.txt", "https://example.com"],
+        );
+    }
+
+    #[test]
+    fn empty_argv_is_an_error() {
+        assert!(matches!(
+            launcher(&[], None).command(&item(&["%U"]), &[]),
+            Err(FuzzydError::LaunchError(_)),
+        ));
+        // The command line is only for display, it's never split into an argv
+        let item = FuzzyItem { exec: "/bin/sh -c true".to_string(), ..item(&[]) };
+        assert!(matches!(launcher(&[], None).command(&item, &[]), Err(FuzzydError::LaunchError(_))));
     }
 }
\ No newline at end of file
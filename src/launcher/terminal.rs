@@ -0,0 +1,63 @@
+use crate::config::TerminalConfig;
+use crate::launcher::{escape_field_codes, resolve_argv};
+use crate::sources::preferred_terminal;
+use std::path::Path;
+
+// Terminals tried in order when none is configured, with the flag that
+// makes them run the remaining arguments as a command
+const FALLBACK_TERMINALS: &[&str] = &[
+    "foot -e",
+    "alacritty -e",
+    "kitty",
+    "wezterm start --",
+    "gnome-terminal --",
+    "konsole -e",
+    "xterm -e",
+];
+
+/// Returns the argv prefix used to run something inside a terminal
+/// emulator, with the executable resolved to an absolute path. Without a
+/// configured command the terminal is found through xdg-terminal-exec,
+/// then its xdg-terminals.list files, then a list of common terminals.
+pub fn terminal_command(config: &TerminalConfig) -> Option<Vec<String>> {
+    match &config.command {
+        Some(command) => resolve_argv(command),
+        None => resolve_argv("xdg-terminal-exec")
+            .or_else(preferred_terminal)
+            .or_else(|| FALLBACK_TERMINALS.iter().find_map(|command| resolve_argv(command))),
+    }
+}
+
+/// Wraps a desktop entry style argv in a terminal command, setting the
+/// window title and app ID for the terminals known to take them.
+pub fn terminal_argv(terminal: &[String], title: &str, app_id: &str, argv: &[String]) -> Vec<String> {
+    let mut prefix: Vec<String> = terminal.iter().map(|arg| escape_field_codes(arg)).collect();
+    let executable = prefix.first()
+        .and_then(|executable| Path::new(executable).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let flags = match executable.as_str() {
+        "xdg-terminal-exec" | "foot" => vec![format!("--title={}", title), format!("--app-id={}", app_id)],
+        "alacritty" | "kitty" => vec!["--title".to_string(), title.to_string(), "--class".to_string(), app_id.to_string()],
+        "wezterm" => vec!["--class".to_string(), app_id.to_string()],
+        "xterm" => vec!["-T".to_string(), title.to_string(), "-class".to_string(), app_id.to_string()],
+        _ => Vec::new(),
+    };
+
+    // The flags go before the option that starts the command
+    let at = match prefix.last().map(String::as_str) {
+        Some("-e" | "-x" | "--") if prefix.len() > 1 => prefix.len() - 1,
+        _ => prefix.len(),
+    };
+    prefix.splice(at..at, flags.iter().map(|flag| escape_field_codes(flag)));
+    prefix.extend(argv.iter().cloned());
+    prefix
+}
+
+/// Runs an argv, already escaped for `expand_field_codes`, in the terminal.
+pub fn in_terminal(terminal: &[String], argv: &[String]) -> Vec<String> {
+    terminal.iter()
+        .map(|arg| escape_field_codes(arg))
+        .chain(argv.iter().cloned())
+        .collect()
+}
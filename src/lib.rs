@@ -4,15 +4,21 @@ pub mod sources;
 pub mod launcher;
 pub mod config;
 pub mod error;
+pub mod uri;
+pub mod clipboard;
+pub mod mime;
+pub mod mimeapps;
+pub mod menu;
+pub mod icons;
+pub mod desktop_file;
 
 use fuzzy::FuzzyFinder;
-use crate::fuzzy::FuzzyItem;
-use sources::Source;
+use crate::fuzzy::{FuzzyItem, ItemAction};
+use sources::{Source, expand_snippet};
 use launcher::SystemdLauncher;
 use ui::TerminalUI;
 use config::Config;
 use error::FuzzydError;
-use dirs;
 use std::path::PathBuf;
 use std::time::Instant;
 use rayon::prelude::*;
@@ -22,6 +28,7 @@ pub struct Fuzzyd {
     launcher: SystemdLauncher,
     config: Config,
     finder: FuzzyFinder,
+    print: bool,
 }
 
 impl Fuzzyd {
@@ -31,6 +38,8 @@ impl Fuzzyd {
         config_path: Option<PathBuf>,
         history_file: Option<PathBuf>,
         dry_run: bool,
+        print: bool,
+        show_all: bool,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let start_time = Instant::now();
 
@@ -39,6 +48,7 @@ impl Fuzzyd {
             None => Config::load()?,
         };
         config.debug = debug;  // Override the config debug setting with the command-line flag
+        config.desktop.show_hidden |= show_all;
 
         let history_file = history_file.or_else(|| {
             config.history.file.as_ref().map(PathBuf::from)
@@ -56,20 +66,30 @@ impl Fuzzyd {
         // Parallelize the loading of items from different sources
         let source_results: Vec<_> = sources.par_iter().map(|source| {
             let source_start_time = Instant::now();
-            let source_finder = source.get_finder();
+            let source_finder = source.get_finder(&config);
             let items = source_finder.find_entries();
+            let providers = source_finder.query_providers();
+            let warnings = source_finder.warnings();
             let item_count = items.len();
             let source_duration = source_start_time.elapsed();
-            (source, items, item_count, source_duration)
+            (source, items, providers, warnings, item_count, source_duration)
         }).collect();
 
         let mut total_items = 0;
-        for (source, items, item_count, source_duration) in source_results {
+        let mut menu = None;
+        for (source, items, providers, warnings, item_count, source_duration) in source_results {
+            if *source == Source::Desktop && config.desktop.menu {
+                menu = menu::load_menu(&items);
+            }
             finder.add_items(items);
+            finder.add_providers(providers);
             total_items += item_count;
 
             if config.debug {
                 println!("Loaded {} items from {:?} source in {:?}", item_count, source, source_duration);
+                for warning in warnings {
+                    println!("{}", warning);
+                }
             }
         }
 
@@ -80,10 +100,11 @@ impl Fuzzyd {
             println!("Total time taken to initialize: {:?}", initialization_time);
         }
 
-        let ui = TerminalUI::new(config.ui.clone(), config.debug);
+        let mut ui = TerminalUI::new(config.ui.clone(), config.debug);
+        ui.set_menu(menu);
         let launcher = SystemdLauncher::new(dry_run, &config.systemd_run);
 
-        Ok(Fuzzyd { ui, launcher, config, finder })
+        Ok(Fuzzyd { ui, launcher, config, finder, print })
     }
 
     pub fn run(&mut self) -> Result<(), FuzzydError> {
@@ -92,21 +113,38 @@ impl Fuzzyd {
             println!("Enter your search query or press Esc with an empty query to exit.");
         }
 
-        loop {
-            match self.ui.run(&mut self.finder)? {
-                Some(item) => {
+        match self.ui.run(&mut self.finder)? {
+            Some(item) => match &item.action {
+                ItemAction::Launch if self.print => println!("{}", item.exec),
+                ItemAction::Launch => {
                     if self.config.debug {
                         println!("Launching: {}", item.exec);
                     }
                     self.finder.record_usage(&item.exec);
                     self.launcher.launch(&item)?;
-                    break; // Exit after launching an application
                 }
-                None => {
+                ItemAction::Run if self.print => println!("{}", item.exec),
+                ItemAction::Run => {
                     if self.config.debug {
-                        println!("Exiting fuzzyd. Goodbye!");
+                        println!("Running: {}", item.exec);
+                    }
+                    self.launcher.spawn(&item)?;
+                }
+                ItemAction::Copy(text) => self.output_text(text)?,
+                ItemAction::OpenWith(target) => self.open(std::slice::from_ref(target))?,
+                ItemAction::OpenDefault(target) => self.open_default(&item, target)?,
+                // Submenus are opened by the UI itself
+                ItemAction::Browse(_) => {}
+                ItemAction::Snippet(text) => {
+                    let ui = &mut self.ui;
+                    if let Some(text) = expand_snippet(text, &self.config.clipboard, |name| ui.prompt(name))? {
+                        self.output_text(&text)?;
                     }
-                    break;
+                }
+            },
+            None => {
+                if self.config.debug {
+                    println!("Exiting fuzzyd. Goodbye!");
                 }
             }
         }
@@ -114,6 +152,96 @@ impl Fuzzyd {
         Ok(())
     }
 
+    // Text results are printed instead of copied when picking for a script
+    fn output_text(&self, text: &str) -> Result<(), FuzzydError> {
+        if self.print {
+            println!("{}", text);
+            return Ok(());
+        }
+        if self.config.debug {
+            println!("Copying: {}", text);
+        }
+        clipboard::copy(text, &self.config.clipboard)
+    }
+
+    /// Picks an application for the files or URLs among the desktop entries
+    /// that can open them, the default application and best MIME type
+    /// matches first, and launches it.
+    pub fn open(&mut self, targets: &[String]) -> Result<(), FuzzydError> {
+        let mime_types: Vec<String> = targets.iter().map(|target| mime::mime_type(target)).collect();
+        let terminal = launcher::terminal_command(&self.config.terminal);
+        let openers: Vec<FuzzyItem> = sources::handlers(terminal.as_deref(), self.config.ui.nested_actions)
+            .into_iter()
+            .filter(|item| item.argv.iter().any(|arg| ["%f", "%F", "%u", "%U"].iter().any(|code| arg.contains(code))))
+            .collect();
+
+        // The default application from mimeapps.list, if all targets share one
+        let is_opener = |id: &str| openers.iter().any(|item| item.id.as_deref() == Some(id));
+        let defaults: Vec<Option<String>> = mime_types.iter()
+            .map(|mime_type| mimeapps::default_application(mime_type, is_opener))
+            .collect();
+        let default_id = defaults.first().cloned().flatten()
+            .filter(|id| defaults.iter().all(|default| default.as_ref() == Some(id)));
+
+        // Every target has to match, and the worst match decides the rank
+        let rank = |item: &FuzzyItem| {
+            if item.id.is_some() && item.id == default_id {
+                return 3;
+            }
+            mime_types.iter().map(|mime_type| mime::match_level(&item.mime_types, mime_type)).min().unwrap_or(0)
+        };
+        let matching: Vec<FuzzyItem> = openers.iter()
+            .filter(|item| rank(item) > 0)
+            .map(|item| FuzzyItem { priority: item.priority + rank(item), ..item.clone() })
+            .collect();
+
+        // Openers are listed by match, not by menu category
+        self.ui.set_menu(None);
+        self.finder.clear();
+        if matching.is_empty() {
+            // Nothing claims the type, so offer everything that takes files
+            self.finder.add_items(openers);
+        } else {
+            self.finder.add_items(matching);
+        }
+
+        if let Some(item) = self.ui.run(&mut self.finder)? {
+            if self.print {
+                let argv = launcher::expand_field_codes(&item.argv, targets);
+                println!("{}", argv.join(" "));
+                return Ok(());
+            }
+            if self.config.debug {
+                println!("Opening {:?} with: {}", targets, item.exec);
+            }
+            self.finder.record_usage(&item.exec);
+            self.launcher.launch_with(&item, targets)?;
+        }
+        Ok(())
+    }
+
+    /// Launches the default application for the item's file or URL, which
+    /// is only looked up now.
+    fn open_default(&mut self, item: &FuzzyItem, target: &str) -> Result<(), FuzzydError> {
+        let terminal = launcher::terminal_command(&self.config.terminal);
+        let argv = sources::open_argv(target, terminal.as_deref())
+            .ok_or_else(|| FuzzydError::LaunchError(format!("No application found to open {}", target)))?;
+        let opener = FuzzyItem {
+            exec: launcher::expand_field_codes(&argv, &[]).join(" "),
+            argv,
+            ..item.clone()
+        };
+        if self.print {
+            println!("{}", opener.exec);
+            return Ok(());
+        }
+        if self.config.debug {
+            println!("Opening {} with: {}", target, opener.exec);
+        }
+        self.finder.record_usage(&item.exec);
+        self.launcher.launch(&opener)
+    }
+
     pub fn launch(&mut self, item: &FuzzyItem) -> Result<(), FuzzydError> {
         self.finder.record_usage(&item.exec);
         self.launcher.launch(item)
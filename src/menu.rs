@@ -0,0 +1,437 @@
+use crate::fuzzy::FuzzyItem;
+use crate::sources::directory_entry;
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xdg::BaseDirectories;
+
+// Menu files that merge each other are cut off at this depth
+const MAX_MERGE_DEPTH: usize = 8;
+
+/// A category of the application menu with the desktop entries sorted
+/// into it.
+#[derive(Clone, Default)]
+pub struct Menu {
+    pub name: String,
+    pub comment: Option<String>,
+    /// Icon theme name from the menu's .directory file
+    pub icon: Option<String>,
+    pub submenus: Vec<Menu>,
+    pub items: Vec<FuzzyItem>,
+}
+
+/// A `<Menu>` element, with the contents of merged files folded in.
+#[derive(Default)]
+struct MenuDef {
+    name: String,
+    directory: Option<String>,
+    /// `<Include>` (true) and `<Exclude>` (false) rules, applied in order
+    rules: Vec<(bool, Rule)>,
+    submenus: Vec<MenuDef>,
+    only_unallocated: Option<bool>,
+    deleted: Option<bool>,
+    /// `<Move>` pairs of old and new paths of submenus, relative to this menu
+    moves: Vec<(String, String)>,
+}
+
+/// A matching rule from `<Include>` or `<Exclude>`.
+enum Rule {
+    Filename(String),
+    Category(String),
+    All,
+    And(Vec<Rule>),
+    Or(Vec<Rule>),
+    Not(Vec<Rule>),
+}
+
+impl Rule {
+    fn matches(&self, item: &FuzzyItem) -> bool {
+        match self {
+            Rule::Filename(id) => item.id.as_deref() == Some(id.as_str()),
+            Rule::Category(category) => item.categories.contains(category),
+            Rule::All => true,
+            Rule::And(rules) => rules.iter().all(|rule| rule.matches(item)),
+            Rule::Or(rules) => rules.iter().any(|rule| rule.matches(item)),
+            Rule::Not(rules) => !rules.iter().any(|rule| rule.matches(item)),
+        }
+    }
+}
+
+impl MenuDef {
+    fn includes(&self, item: &FuzzyItem) -> bool {
+        self.rules.iter().fold(false, |included, (include, rule)| {
+            if rule.matches(item) { *include } else { included }
+        })
+    }
+
+    // Folds in a merged file or a later menu with the same name
+    fn absorb(&mut self, other: MenuDef) {
+        self.directory = other.directory.or(self.directory.take());
+        self.rules.extend(other.rules);
+        self.submenus.extend(other.submenus);
+        self.only_unallocated = other.only_unallocated.or(self.only_unallocated);
+        self.deleted = other.deleted.or(self.deleted);
+        self.moves.extend(other.moves);
+    }
+
+    // Takes out the submenu at a "/"-separated path
+    fn remove(&mut self, path: &str) -> Option<MenuDef> {
+        let (name, rest) = match path.split_once('/') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (path, None),
+        };
+        let index = self.submenus.iter().position(|submenu| submenu.name == name)?;
+        match rest {
+            Some(rest) => self.submenus[index].remove(rest),
+            None => Some(self.submenus.remove(index)),
+        }
+    }
+
+    // The submenu at a "/"-separated path, created if it doesn't exist
+    fn submenu_at(&mut self, path: &str) -> &mut MenuDef {
+        let (name, rest) = match path.split_once('/') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (path, None),
+        };
+        let index = match self.submenus.iter().position(|submenu| submenu.name == name) {
+            Some(index) => index,
+            None => {
+                self.submenus.push(MenuDef { name: name.to_string(), ..Default::default() });
+                self.submenus.len() - 1
+            }
+        };
+        match rest {
+            Some(rest) => self.submenus[index].submenu_at(rest),
+            None => &mut self.submenus[index],
+        }
+    }
+}
+
+/// Loads `applications.menu`, prefixed with `XDG_MENU_PREFIX`, from the
+/// XDG config dirs and sorts the desktop entries into its categories
+/// following the Desktop Menu spec. Desktop actions are left out, and so
+/// are menus that end up empty.
+pub fn load_menu(items: &[FuzzyItem]) -> Option<Menu> {
+    build_menu(&MenuLoader::new(), items)
+}
+
+fn build_menu(loader: &MenuLoader, items: &[FuzzyItem]) -> Option<Menu> {
+    let file_name = format!("{}applications.menu", loader.prefix);
+    let path = loader.dirs.iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())?;
+    let def = consolidate(apply_moves(consolidate(loader.parse_file(&path, 0)?)));
+
+    let entries: Vec<&FuzzyItem> = items.iter()
+        .filter(|item| item.id.as_ref().is_some_and(|id| !id.contains(':')))
+        .collect();
+
+    // Menus with <OnlyUnallocated> get the entries no other menu took
+    let allocated_menu = allocate(&def, &entries, false, &HashSet::new())?;
+    let mut allocated = HashSet::new();
+    collect_ids(&allocated_menu, &mut allocated);
+    let unallocated_menu = allocate(&def, &entries, true, &allocated)?;
+
+    prune(combine(allocated_menu, unallocated_menu))
+}
+
+struct MenuLoader {
+    dirs: Vec<PathBuf>,
+    prefix: String,
+}
+
+impl MenuLoader {
+    fn new() -> Self {
+        let mut dirs = Vec::new();
+        if let Ok(xdg_dirs) = BaseDirectories::new() {
+            dirs.push(xdg_dirs.get_config_home().join("menus"));
+            dirs.extend(xdg_dirs.get_config_dirs().into_iter().map(|dir| dir.join("menus")));
+        }
+        let prefix = env::var("XDG_MENU_PREFIX").unwrap_or_default();
+        MenuLoader { dirs, prefix }
+    }
+
+    fn parse_file(&self, path: &Path, depth: usize) -> Option<MenuDef> {
+        let contents = fs::read_to_string(path).ok()?;
+        let options = ParsingOptions { allow_dtd: true, ..Default::default() };
+        let document = Document::parse_with_options(&contents, options).ok()?;
+        let root = document.root_element();
+        if !root.has_tag_name("Menu") {
+            return None;
+        }
+        let mut def = MenuDef::default();
+        self.parse_menu(root, path, &mut def, depth);
+        Some(def)
+    }
+
+    fn parse_menu(&self, node: Node, path: &Path, def: &mut MenuDef, depth: usize) {
+        let base_dir = path.parent().unwrap_or(Path::new("/"));
+        for child in node.children().filter(Node::is_element) {
+            let text = child.text().unwrap_or_default().trim();
+            match child.tag_name().name() {
+                "Name" => def.name = text.to_string(),
+                "Directory" => def.directory = Some(text.to_string()),
+                "Include" => def.rules.push((true, Rule::Or(parse_rules(child)))),
+                "Exclude" => def.rules.push((false, Rule::Or(parse_rules(child)))),
+                "OnlyUnallocated" => def.only_unallocated = Some(true),
+                "NotOnlyUnallocated" => def.only_unallocated = Some(false),
+                "Deleted" => def.deleted = Some(true),
+                "NotDeleted" => def.deleted = Some(false),
+                "Move" => {
+                    let path = |tag: &str| {
+                        child.children()
+                            .find(|node| node.has_tag_name(tag))
+                            .and_then(|node| node.text())
+                            .map(|text| text.trim().trim_matches('/').to_string())
+                    };
+                    if let (Some(old), Some(new)) = (path("Old"), path("New")) {
+                        def.moves.push((old, new));
+                    }
+                }
+                "Menu" => {
+                    let mut submenu = MenuDef::default();
+                    self.parse_menu(child, path, &mut submenu, depth);
+                    def.submenus.push(submenu);
+                }
+                "MergeFile" => {
+                    let merged = match child.attribute("type") {
+                        Some("parent") => self.parent_file(path),
+                        _ => Some(base_dir.join(text)),
+                    };
+                    if let Some(merged) = merged {
+                        self.merge_file(&merged, def, depth);
+                    }
+                }
+                "MergeDir" => self.merge_dir(&base_dir.join(text), def, depth),
+                "DefaultMergeDirs" => {
+                    let merge_dir = format!("{}applications-merged", self.prefix);
+                    for dir in self.dirs.iter().rev() {
+                        self.merge_dir(&dir.join(&merge_dir), def, depth);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // The root <Menu> of a merged file replaces the merge element, except
+    // for its name
+    fn merge_file(&self, path: &Path, def: &mut MenuDef, depth: usize) {
+        if depth >= MAX_MERGE_DEPTH {
+            return;
+        }
+        if let Some(merged) = self.parse_file(path, depth + 1) {
+            def.absorb(merged);
+        }
+    }
+
+    fn merge_dir(&self, dir: &Path, def: &mut MenuDef, depth: usize) {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "menu"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            self.merge_file(&path, def, depth);
+        }
+    }
+
+    // The file with the same path in the next less important config dir
+    fn parent_file(&self, path: &Path) -> Option<PathBuf> {
+        let (index, relative) = self.dirs.iter()
+            .enumerate()
+            .find_map(|(index, dir)| Some((index, path.strip_prefix(dir).ok()?)))?;
+        self.dirs[index + 1..].iter()
+            .map(|dir| dir.join(relative))
+            .find(|path| path.is_file())
+    }
+}
+
+fn parse_rules(node: Node) -> Vec<Rule> {
+    node.children()
+        .filter(Node::is_element)
+        .filter_map(|child| {
+            let text = child.text().unwrap_or_default().trim().to_string();
+            match child.tag_name().name() {
+                "Filename" => Some(Rule::Filename(text)),
+                "Category" => Some(Rule::Category(text)),
+                "All" => Some(Rule::All),
+                "And" => Some(Rule::And(parse_rules(child))),
+                "Or" => Some(Rule::Or(parse_rules(child))),
+                "Not" => Some(Rule::Not(parse_rules(child))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// Submenus with the same name are merged, the later ones taking precedence
+fn consolidate(mut def: MenuDef) -> MenuDef {
+    let mut submenus: Vec<MenuDef> = Vec::new();
+    for submenu in def.submenus.drain(..) {
+        match submenus.iter_mut().find(|existing| existing.name == submenu.name) {
+            Some(existing) => existing.absorb(submenu),
+            None => submenus.push(submenu),
+        }
+    }
+    def.submenus = submenus.into_iter().map(consolidate).collect();
+    def
+}
+
+// Moves submenus to their new paths, in order and the deepest menus
+// first. A menu moved onto an existing one is merged into it
+fn apply_moves(mut def: MenuDef) -> MenuDef {
+    def.submenus = def.submenus.into_iter().map(apply_moves).collect();
+    for (old, new) in std::mem::take(&mut def.moves) {
+        if let Some(moved) = def.remove(&old) {
+            def.submenu_at(&new).absorb(moved);
+        }
+    }
+    def
+}
+
+// Fills the menus that match the pass, either the regular ones or those
+// with <OnlyUnallocated>, skipping deleted menus
+fn allocate(def: &MenuDef, entries: &[&FuzzyItem], only_unallocated: bool, allocated: &HashSet<String>) -> Option<Menu> {
+    if def.deleted == Some(true) {
+        return None;
+    }
+    let mut items = Vec::new();
+    if def.only_unallocated.unwrap_or(false) == only_unallocated {
+        items = entries.iter()
+            .filter(|item| def.includes(item))
+            .filter(|item| !item.id.as_ref().is_some_and(|id| allocated.contains(id)))
+            .map(|item| (*item).clone())
+            .collect();
+    }
+    let directory = def.directory.as_deref().and_then(directory_entry);
+    Some(Menu {
+        name: directory.as_ref().map_or_else(|| def.name.clone(), |directory| directory.name.clone()),
+        comment: directory.as_ref().and_then(|directory| directory.comment.clone()),
+        icon: directory.and_then(|directory| directory.icon),
+        submenus: def.submenus.iter()
+            .filter_map(|submenu| allocate(submenu, entries, only_unallocated, allocated))
+            .collect(),
+        items,
+    })
+}
+
+fn collect_ids(menu: &Menu, ids: &mut HashSet<String>) {
+    ids.extend(menu.items.iter().filter_map(|item| item.id.clone()));
+    for submenu in &menu.submenus {
+        collect_ids(submenu, ids);
+    }
+}
+
+// Both passes build the same tree, so their menus line up
+fn combine(mut menu: Menu, other: Menu) -> Menu {
+    menu.items.extend(other.items);
+    menu.submenus = menu.submenus.into_iter()
+        .zip(other.submenus)
+        .map(|(submenu, other)| combine(submenu, other))
+        .collect();
+    menu
+}
+
+// Drops empty menus and sorts the rest by name
+fn prune(mut menu: Menu) -> Option<Menu> {
+    menu.submenus = menu.submenus.into_iter().filter_map(prune).collect();
+    if menu.items.is_empty() && menu.submenus.is_empty() {
+        return None;
+    }
+    menu.submenus.sort_by_key(|submenu| submenu.name.to_lowercase());
+    menu.items.sort_by_key(|item| item.display.to_lowercase());
+    Some(menu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader() -> MenuLoader {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/menus/menus");
+        MenuLoader { dirs: vec![dir], prefix: String::new() }
+    }
+
+    fn item(id: &str, categories: &[&str]) -> FuzzyItem {
+        FuzzyItem {
+            display: id.trim_end_matches(".desktop").to_string(),
+            id: Some(id.to_string()),
+            categories: categories.iter().map(|category| category.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    // Each menu as "path: items"
+    fn outline(menu: &Menu, path: &str, lines: &mut Vec<String>) {
+        let items: Vec<&str> = menu.items.iter().map(|item| item.display.as_str()).collect();
+        lines.push(format!("{}: {}", path, items.join(" ")));
+        for submenu in &menu.submenus {
+            outline(submenu, &format!("{}/{}", path, submenu.name), lines);
+        }
+    }
+
+    #[test]
+    fn menu_files_are_parsed_and_merged() {
+        let def = loader().parse_file(&loader().dirs[0].join("applications.menu"), 0).unwrap();
+        assert_eq!(def.name, "Applications");
+        let names: Vec<&str> = def.submenus.iter().map(|submenu| submenu.name.as_str()).collect();
+        assert_eq!(names, ["Education", "Development", "Graphics", "Games", "Internet", "Other", "Development"]);
+        assert_eq!(def.moves, [("Internet".to_string(), "Network/Web".to_string())]);
+        // The rules of the merged file take the place of <MergeFile>
+        let development = &def.submenus[1];
+        let includes: Vec<bool> = development.rules.iter().map(|(include, _)| *include).collect();
+        assert_eq!(includes, [true, false, true]);
+        assert_eq!(def.submenus[3].deleted, Some(true));
+        assert_eq!(def.submenus[5].only_unallocated, Some(true));
+    }
+
+    #[test]
+    fn entries_are_allocated_to_menus() {
+        let items = [
+            item("emacs.desktop", &["Development", "TextEditor"]),
+            item("code.desktop", &["IDE"]),
+            item("gimp.desktop", &["Graphics"]),
+            item("eog.desktop", &["Graphics", "Viewer"]),
+            item("supertux.desktop", &["Game"]),
+            item("firefox.desktop", &["Network"]),
+            FuzzyItem { id: Some("firefox.desktop:new-window".to_string()), ..item("firefox-window.desktop", &["Network"]) },
+            item("gcompris.desktop", &["Education"]),
+        ];
+        let menu = build_menu(&loader(), &items).unwrap();
+        let mut lines = Vec::new();
+        outline(&menu, "", &mut lines);
+        assert_eq!(lines, [
+            ": ",
+            "/Development: code emacs",
+            "/Education: gcompris",
+            "/Graphics: gimp",
+            "/Network: ",
+            "/Network/Web: firefox",
+            // Deleted menus leave their entries unallocated
+            "/Other: eog supertux",
+        ]);
+    }
+
+    #[test]
+    fn menus_can_be_moved_onto_existing_ones() {
+        let mut def = MenuDef {
+            submenus: vec![
+                MenuDef { name: "Old".to_string(), rules: vec![(true, Rule::All)], ..Default::default() },
+                MenuDef { name: "New".to_string(), deleted: Some(false), ..Default::default() },
+            ],
+            moves: vec![("Old".to_string(), "New".to_string()), ("Missing".to_string(), "Elsewhere".to_string())],
+            ..Default::default()
+        };
+        def = apply_moves(def);
+        assert_eq!(def.submenus.len(), 1);
+        assert_eq!(def.submenus[0].name, "New");
+        assert_eq!(def.submenus[0].rules.len(), 1);
+        assert_eq!(def.submenus[0].deleted, Some(false));
+    }
+}
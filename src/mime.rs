@@ -0,0 +1,555 @@
+use crate::uri::uri_to_path;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use xdg::BaseDirectories;
+
+// Magic rules are only checked against the start of a file
+const MAX_MAGIC_EXTENT: usize = 64 * 1024;
+
+/// A filename pattern from the shared-mime-info globs2 files.
+struct Glob {
+    weight: u32,
+    mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+/// One rule of a magic section, nested below the rules with a lower indent.
+struct MagicRule {
+    indent: usize,
+    offset: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+    range: usize,
+}
+
+struct MagicSection {
+    priority: u32,
+    mime_type: String,
+    rules: Vec<MagicRule>,
+}
+
+/// Determines the MIME type of a file path or URL. URLs other than file://
+/// map to `x-scheme-handler/<scheme>` and directories to `inode/directory`.
+/// Files are matched against the shared-mime-info filename globs, then
+/// against the magic rules for their contents.
+pub fn mime_type(target: &str) -> String {
+    let path = match url_scheme(target) {
+        Some("file") => uri_to_path(target).unwrap_or_default(),
+        Some(scheme) => return format!("x-scheme-handler/{}", scheme.to_lowercase()),
+        None => PathBuf::from(target),
+    };
+    if path.is_dir() {
+        return "inode/directory".to_string();
+    }
+    let mime_type = glob_mime_type(globs(), &path)
+        .or_else(|| sniff_mime_type(&path))
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    canonical(&mime_type)
+}
+
+/// The type itself followed by its ancestors, from the subclasses file and
+/// the implicit text/plain and application/octet-stream parents.
+pub fn with_parents(mime_type: &str) -> Vec<String> {
+    let mut types = vec![canonical(mime_type)];
+    let mut i = 0;
+    while i < types.len() {
+        let parents = subclasses().get(&types[i]).cloned().unwrap_or_default();
+        for parent in parents.iter().map(|parent| canonical(parent)) {
+            if !types.contains(&parent) {
+                types.push(parent);
+            }
+        }
+        i += 1;
+    }
+
+    let media_type = mime_type.split('/').next().unwrap_or_default();
+    if media_type == "text" && !types.iter().any(|t| t == "text/plain") {
+        types.push("text/plain".to_string());
+    }
+    let streamable = !matches!(media_type, "inode" | "x-scheme-handler");
+    if streamable && !types.iter().any(|t| t == "application/octet-stream") {
+        types.push("application/octet-stream".to_string());
+    }
+    types
+}
+
+fn canonical(mime_type: &str) -> String {
+    let mime_type = mime_type.to_lowercase();
+    aliases().get(&mime_type).cloned().unwrap_or(mime_type)
+}
+
+fn aliases() -> &'static HashMap<String, String> {
+    static ALIASES: OnceLock<HashMap<String, String>> = OnceLock::new();
+    ALIASES.get_or_init(|| {
+        read_pairs("aliases").into_iter().collect()
+    })
+}
+
+fn subclasses() -> &'static HashMap<String, Vec<String>> {
+    static SUBCLASSES: OnceLock<HashMap<String, Vec<String>>> = OnceLock::new();
+    SUBCLASSES.get_or_init(|| {
+        let mut subclasses: HashMap<String, Vec<String>> = HashMap::new();
+        for (child, parent) in read_pairs("subclasses") {
+            subclasses.entry(child).or_default().push(parent);
+        }
+        subclasses
+    })
+}
+
+// The aliases and subclasses files hold two lowercased types per line
+fn read_pairs(file_name: &str) -> Vec<(String, String)> {
+    mime_dirs().iter()
+        .filter_map(|dir| fs::read_to_string(dir.join(file_name)).ok())
+        .flat_map(|contents| {
+            contents.lines()
+                .filter_map(|line| {
+                    let (first, second) = line.trim().split_once(' ')?;
+                    Some((first.to_lowercase(), second.to_lowercase()))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Identifies a file by its contents, falling back to text/plain for
+/// files that look like text.
+fn sniff_mime_type(path: &Path) -> Option<String> {
+    let mut data = Vec::new();
+    File::open(path).ok()?
+        .take(MAX_MAGIC_EXTENT as u64)
+        .read_to_end(&mut data)
+        .ok()?;
+
+    let magic_type = magic().iter()
+        .find(|section| section_matches(&section.rules, &data))
+        .map(|section| section.mime_type.clone());
+    if magic_type.is_some() {
+        return magic_type;
+    }
+
+    // Like shared-mime-info, treat files without control bytes as text
+    let head = &data[..data.len().min(128)];
+    let is_text = head.iter().all(|&b| b >= 0x20 || b"\t\n\r\x0c\x1b".contains(&b));
+    is_text.then(|| "text/plain".to_string())
+}
+
+fn magic() -> &'static [MagicSection] {
+    static MAGIC: OnceLock<Vec<MagicSection>> = OnceLock::new();
+    MAGIC.get_or_init(|| {
+        let mut sections: Vec<MagicSection> = mime_dirs().iter()
+            .filter_map(|dir| fs::read(dir.join("magic")).ok())
+            .flat_map(|data| parse_magic(&data).unwrap_or_default())
+            .collect();
+        sections.sort_by_key(|section| std::cmp::Reverse(section.priority));
+        sections
+    })
+}
+
+/// Parses the binary shared-mime-info magic file.
+fn parse_magic(data: &[u8]) -> Option<Vec<MagicSection>> {
+    let mut rest = data.strip_prefix(b"MIME-Magic\0\n")?;
+    let mut sections: Vec<MagicSection> = Vec::new();
+
+    while !rest.is_empty() {
+        if rest[0] == b'[' {
+            // [priority:mime/type]
+            let end = rest.iter().position(|&b| b == b'\n')?;
+            let header = std::str::from_utf8(&rest[1..end]).ok()?.trim_end_matches(']');
+            let (priority, mime_type) = header.split_once(':')?;
+            sections.push(MagicSection {
+                priority: priority.parse().ok()?,
+                mime_type: mime_type.to_string(),
+                rules: Vec::new(),
+            });
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        // [indent]>offset=length value[&mask][~word-size][+range]
+        let (indent, after) = parse_number(rest, b'>');
+        let (offset, after) = parse_number(after, b'=');
+        if after.len() < 2 {
+            return None;
+        }
+        let length = u16::from_be_bytes([after[0], after[1]]) as usize;
+        let mut after = &after[2..];
+        if after.len() < length {
+            return None;
+        }
+        let mut value = after[..length].to_vec();
+        after = &after[length..];
+
+        let mut mask = None;
+        if after.first() == Some(&b'&') && after.len() > length {
+            mask = Some(after[1..1 + length].to_vec());
+            after = &after[1 + length..];
+        }
+        let mut word_size = 1;
+        if after.first() == Some(&b'~') {
+            let (size, next) = parse_number(&after[1..], 0);
+            word_size = size.unwrap_or(1).max(1);
+            after = next;
+        }
+        let mut range = 1;
+        if after.first() == Some(&b'+') {
+            let (length, next) = parse_number(&after[1..], 0);
+            range = length.unwrap_or(1).max(1);
+            after = next;
+        }
+        // Skip anything this parser doesn't know about up to the newline
+        let end = after.iter().position(|&b| b == b'\n')?;
+        rest = &after[end + 1..];
+
+        // Word-sized values are stored big endian
+        if word_size > 1 && cfg!(target_endian = "little") {
+            for chunk in value.chunks_mut(word_size) {
+                chunk.reverse();
+            }
+            if let Some(mask) = &mut mask {
+                for chunk in mask.chunks_mut(word_size) {
+                    chunk.reverse();
+                }
+            }
+        }
+
+        if let Some(section) = sections.last_mut() {
+            section.rules.push(MagicRule { indent: indent.unwrap_or(0), offset: offset.unwrap_or(0), value, mask, range });
+        }
+    }
+    Some(sections)
+}
+
+// Reads a decimal number up to a separator, which is consumed if present
+fn parse_number(data: &[u8], separator: u8) -> (Option<usize>, &[u8]) {
+    let digits = data.iter().take_while(|b| b.is_ascii_digit()).count();
+    let number = std::str::from_utf8(&data[..digits]).ok().and_then(|digits| digits.parse().ok());
+    let rest = &data[digits..];
+    match rest.first() {
+        Some(&b) if b == separator && separator != 0 => (number, &rest[1..]),
+        _ => (number, rest),
+    }
+}
+
+// A section matches when one of its top level rules matches
+fn section_matches(rules: &[MagicRule], data: &[u8]) -> bool {
+    (0..rules.len())
+        .filter(|&i| rules[i].indent == 0)
+        .any(|i| rule_tree_matches(rules, i, data))
+}
+
+// A rule matches when its value is found and, if it has nested rules, one
+// of them matches as well
+fn rule_tree_matches(rules: &[MagicRule], i: usize, data: &[u8]) -> bool {
+    let rule = &rules[i];
+    if !rule_matches(rule, data) {
+        return false;
+    }
+    let mut children = rules[i + 1..].iter()
+        .enumerate()
+        .take_while(|(_, child)| child.indent > rule.indent)
+        .filter(|(_, child)| child.indent == rule.indent + 1)
+        .map(|(j, _)| i + 1 + j)
+        .peekable();
+    children.peek().is_none() || children.any(|j| rule_tree_matches(rules, j, data))
+}
+
+fn rule_matches(rule: &MagicRule, data: &[u8]) -> bool {
+    (rule.offset..rule.offset + rule.range).any(|start| {
+        let window = match data.get(start..start + rule.value.len()) {
+            Some(window) => window,
+            None => return false,
+        };
+        match &rule.mask {
+            Some(mask) => window.iter().zip(&rule.value).zip(mask).all(|((&b, &v), &m)| b & m == v & m),
+            None => window == rule.value.as_slice(),
+        }
+    })
+}
+
+/// How well a MIME type list matches a type: 2 for the type itself, 1 for
+/// a wildcard like `image/*`, and 0 otherwise.
+pub fn match_level(mime_types: &[String], mime_type: &str) -> u8 {
+    let media_type = mime_type.split('/').next().unwrap_or_default();
+    mime_types.iter()
+        .map(|candidate| {
+            if candidate.eq_ignore_ascii_case(mime_type) {
+                2
+            } else if candidate.strip_suffix("/*").is_some_and(|media| media.eq_ignore_ascii_case(media_type)) {
+                1
+            } else {
+                0
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// A scheme is letters, digits, "+", "-" and "." before a ":", which rules
+// out relative paths and single-letter drive-like prefixes
+fn url_scheme(target: &str) -> Option<&str> {
+    let (scheme, _) = target.split_once(':')?;
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    valid.then_some(scheme)
+}
+
+fn glob_mime_type(globs: &[Glob], path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_string_lossy();
+    let lowercase = file_name.to_lowercase();
+
+    // The highest weight wins, then the longest pattern
+    globs.iter()
+        .filter(|glob| {
+            let name = if glob.case_sensitive { file_name.as_ref() } else { lowercase.as_str() };
+            glob_matches(&glob.pattern, name)
+        })
+        .max_by_key(|glob| (glob.weight, glob.pattern.len()))
+        .map(|glob| glob.mime_type.clone())
+}
+
+fn globs() -> &'static [Glob] {
+    static GLOBS: OnceLock<Vec<Glob>> = OnceLock::new();
+    GLOBS.get_or_init(|| read_globs(&mime_dirs()))
+}
+
+// A __NOGLOBS__ line discards the globs of its type from the directories
+// after it, so a user can replace the system patterns of a type
+fn read_globs(dirs: &[PathBuf]) -> Vec<Glob> {
+    let mut globs = Vec::new();
+    let mut replaced: HashSet<String> = HashSet::new();
+    for dir in dirs {
+        let contents = match fs::read_to_string(dir.join("globs2")) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        let (dir_globs, cleared) = parse_globs2(&contents);
+        globs.extend(dir_globs.into_iter().filter(|glob| !replaced.contains(&glob.mime_type)));
+        replaced.extend(cleared);
+    }
+    globs
+}
+
+/// Parses a globs2 file into its globs and the types it clears with
+/// __NOGLOBS__.
+fn parse_globs2(contents: &str) -> (Vec<Glob>, Vec<String>) {
+    let mut globs = Vec::new();
+    let mut cleared = Vec::new();
+    // weight:type:pattern[:flags]
+    for line in contents.lines().filter(|line| !line.starts_with('#')) {
+        let mut fields = line.splitn(4, ':');
+        let (weight, mime_type, pattern) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(weight), Some(mime_type), Some(pattern)) => (weight, mime_type, pattern),
+            _ => continue,
+        };
+        if pattern == "__NOGLOBS__" {
+            cleared.push(mime_type.to_string());
+            continue;
+        }
+        let case_sensitive = fields.next().is_some_and(|flags| flags.split(',').any(|flag| flag == "cs"));
+        globs.push(Glob {
+            weight: weight.parse().unwrap_or(50),
+            mime_type: mime_type.to_string(),
+            pattern: if case_sensitive { pattern.to_string() } else { pattern.to_lowercase() },
+            case_sensitive,
+        });
+    }
+    (globs, cleared)
+}
+
+/// The mime directories of every XDG data dir, the user's first.
+pub(crate) fn mime_dirs() -> Vec<PathBuf> {
+    let xdg_dirs = match BaseDirectories::new() {
+        Ok(xdg_dirs) => xdg_dirs,
+        Err(_) => return Vec::new(),
+    };
+    let mut dirs = vec![xdg_dirs.get_data_home()];
+    dirs.extend(xdg_dirs.get_data_dirs());
+    dirs.into_iter().map(|dir| dir.join("mime")).collect()
+}
+
+// Supports the "*" and "?" wildcards and character classes
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some('[') => match class_matches(&pattern[p..], name[n]) {
+                Some((true, length)) => {
+                    p += length;
+                    n += 1;
+                }
+                _ => match backtrack {
+                    Some((star, matched)) => {
+                        p = star + 1;
+                        n = matched + 1;
+                        backtrack = Some((star, matched + 1));
+                    }
+                    None => return false,
+                },
+            },
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Returns whether a "[...]" class matches, and the length of the class.
+// "[!...]" matches the characters not in the class, and a "]" right after
+// the opening "[" or "[!" is taken literally
+fn class_matches(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = pattern.get(1) == Some(&'!');
+    let start = if negated { 2 } else { 1 };
+    let end = start + 1 + pattern.get(start + 1..)?.iter().position(|&p| p == ']')?;
+    let class = &pattern[start..end];
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+    Some((matched != negated, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mime")
+    }
+
+    #[test]
+    fn globs_support_wildcards_and_classes() {
+        assert!(glob_matches("*.tar.gz", "backup.tar.gz"));
+        assert!(glob_matches("readme*", "readme"));
+        assert!(glob_matches("?akefile", "Makefile"));
+        assert!(!glob_matches("*.gz", "archive.gz.part"));
+        assert!(glob_matches("[Mm]akefile", "makefile"));
+        assert!(glob_matches("*.[a-c]", "main.b"));
+        assert!(!glob_matches("*.[a-c]", "main.d"));
+        assert!(glob_matches("*.jp[!x]", "photo.jpg"));
+        assert!(!glob_matches("*.jp[!x]", "photo.jpx"));
+        assert!(glob_matches("[]x]", "]"));
+        assert!(glob_matches("[!]]", "x"));
+        assert!(!glob_matches("[!]]", "]"));
+        // An unterminated class never matches
+        assert!(!glob_matches("[abc", "a"));
+    }
+
+    #[test]
+    fn classes_report_their_length() {
+        let class: Vec<char> = "[!a-c]rest".chars().collect();
+        assert_eq!(class_matches(&class, 'b'), Some((false, 6)));
+        assert_eq!(class_matches(&class, 'z'), Some((true, 6)));
+        let class: Vec<char> = "[]a]".chars().collect();
+        assert_eq!(class_matches(&class, ']'), Some((true, 4)));
+        assert_eq!(class_matches(&"[!".chars().collect::<Vec<_>>(), 'a'), None);
+    }
+
+    #[test]
+    fn the_best_glob_wins() {
+        let globs = read_globs(&[fixtures().join("system")]);
+        let mime_type = |name: &str| glob_mime_type(&globs, Path::new(name));
+        // The higher weight wins over the longer pattern, then the longer pattern
+        assert_eq!(mime_type("Makefile").as_deref(), Some("text/x-makefile"));
+        assert_eq!(mime_type("backup.tar.gz").as_deref(), Some("application/x-compressed-tar"));
+        assert_eq!(mime_type("notes.gz").as_deref(), Some("application/gzip"));
+        assert_eq!(mime_type("README.MD").as_deref(), Some("text/markdown"));
+        assert_eq!(mime_type("readme.txt").as_deref(), Some("text/x-readme"));
+        // Case sensitive patterns
+        assert_eq!(mime_type("main.c").as_deref(), Some("text/x-csrc"));
+        assert_eq!(mime_type("main.C").as_deref(), Some("text/x-c++src"));
+        assert_eq!(mime_type("photo.jpx"), None);
+    }
+
+    #[test]
+    fn noglobs_replaces_the_patterns_of_later_dirs() {
+        let globs = read_globs(&[fixtures().join("user"), fixtures().join("system")]);
+        let mime_type = |name: &str| glob_mime_type(&globs, Path::new(name));
+        assert_eq!(mime_type("notes.mdown").as_deref(), Some("text/markdown"));
+        assert_eq!(mime_type("notes.md"), None);
+        assert_eq!(mime_type("notes.markdown"), None);
+        assert_eq!(mime_type("photo.jpg").as_deref(), Some("image/jpeg"));
+
+        let (globs, cleared) = parse_globs2("50:text/markdown:__NOGLOBS__\n50:text/markdown:*.mdown\n");
+        assert_eq!(cleared, ["text/markdown"]);
+        assert_eq!(globs.len(), 1);
+    }
+
+    fn magic_type(sections: &[MagicSection], data: &[u8]) -> Option<String> {
+        sections.iter()
+            .find(|section| section_matches(&section.rules, data))
+            .map(|section| section.mime_type.clone())
+    }
+
+    #[test]
+    fn magic_rules_are_parsed() {
+        let sections = parse_magic(&fs::read(fixtures().join("system/magic")).unwrap()).unwrap();
+        let types: Vec<(u32, &str)> = sections.iter().map(|section| (section.priority, section.mime_type.as_str())).collect();
+        assert_eq!(types, [
+            (50, "image/png"),
+            (80, "application/x-nested"),
+            (40, "application/x-masked"),
+            (30, "application/x-word"),
+            (20, "application/x-future"),
+        ]);
+        assert_eq!(sections[1].rules.iter().map(|rule| rule.indent).collect::<Vec<_>>(), [0, 1, 1]);
+        assert_eq!(sections[2].rules[0].range, 4);
+        assert_eq!(sections[2].rules[0].mask.as_deref(), Some(&b"\x0f"[..]));
+        assert_eq!(sections[4].rules[0].value, b"FUT");
+
+        assert!(parse_magic(b"not magic").is_none());
+        // A value that runs past the end of the file
+        assert!(parse_magic(b"MIME-Magic\0\n[50:text/x-short]\n>0=\x00\x09short\n").is_none());
+    }
+
+    #[test]
+    fn magic_rules_match_contents() {
+        let sections = parse_magic(&fs::read(fixtures().join("system/magic")).unwrap()).unwrap();
+        assert_eq!(magic_type(&sections, b"\x89PNG\r\n\x1a\n....").as_deref(), Some("image/png"));
+        assert_eq!(magic_type(&sections, b"TEST....OK").as_deref(), Some("application/x-nested"));
+        assert_eq!(magic_type(&sections, b"TEST....YES").as_deref(), Some("application/x-nested"));
+        assert_eq!(magic_type(&sections, b"TEST....NO"), None);
+        assert_eq!(magic_type(&sections, b"\x00\x00\x00\x00\x00\x00\x3f").as_deref(), Some("application/x-masked"));
+        assert_eq!(magic_type(&sections, b"\x00\x00\x00\x00\x00\x00\x00\x00\x3f"), None);
+        assert_eq!(magic_type(&sections, &0x1234u16.to_ne_bytes()).as_deref(), Some("application/x-word"));
+        assert_eq!(magic_type(&sections, b"FUTURE").as_deref(), Some("application/x-future"));
+    }
+
+    #[test]
+    fn wildcards_match_below_exact_types() {
+        let types = vec!["image/*".to_string(), "image/png".to_string(), "text/plain".to_string()];
+        assert_eq!(match_level(&types, "image/png"), 2);
+        assert_eq!(match_level(&types, "IMAGE/JPEG"), 1);
+        assert_eq!(match_level(&types, "video/mp4"), 0);
+        assert_eq!(match_level(&[], "image/png"), 0);
+    }
+}
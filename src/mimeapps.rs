@@ -0,0 +1,181 @@
+use crate::mime::with_parents;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use xdg::BaseDirectories;
+
+/// The associations of one mimeapps.list file.
+#[derive(Default)]
+struct MimeApps {
+    defaults: HashMap<String, Vec<String>>,
+    added: HashMap<String, Vec<String>>,
+    removed: HashMap<String, Vec<String>>,
+}
+
+/// Finds the desktop file ID of the default application for a MIME type,
+/// following the XDG MIME Applications spec. Defaults and added
+/// associations from the mimeapps.list files come first, then the
+/// mimeinfo.cache of each data dir, and then the same for every parent
+/// type. `is_installed` tells whether a desktop file ID can be launched.
+pub fn default_application<F>(mime_type: &str, is_installed: F) -> Option<String>
+where
+    F: Fn(&str) -> bool,
+{
+    with_parents(mime_type).iter()
+        .find_map(|mime_type| association(mime_type, &is_installed))
+}
+
+fn association<F>(mime_type: &str, is_installed: &F) -> Option<String>
+where
+    F: Fn(&str) -> bool,
+{
+    let lists = mimeapps_lists();
+    let lookup = |map: &HashMap<String, Vec<String>>| map.get(mime_type).cloned().unwrap_or_default();
+
+    // An installed default in a more important file wins
+    let default = lists.iter()
+        .flat_map(|list| lookup(&list.defaults))
+        .find(|id| is_installed(id));
+    if default.is_some() {
+        return default;
+    }
+
+    // Removed associations hide the ones added by less important files
+    let mut removed = HashSet::new();
+    for list in lists {
+        let added = lookup(&list.added).into_iter()
+            .find(|id| !removed.contains(id) && is_installed(id));
+        if added.is_some() {
+            return added;
+        }
+        removed.extend(lookup(&list.removed));
+    }
+
+    mimeinfo_caches().iter()
+        .flat_map(|cache| cache.get(mime_type).cloned().unwrap_or_default())
+        .find(|id| !removed.contains(id) && is_installed(id))
+}
+
+fn mimeapps_lists() -> &'static [MimeApps] {
+    static LISTS: OnceLock<Vec<MimeApps>> = OnceLock::new();
+    LISTS.get_or_init(|| {
+        mimeapps_list_paths().iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .map(|contents| parse_mimeapps_list(&contents))
+            .collect()
+    })
+}
+
+// Config dirs come before data dirs, and the desktop specific list comes
+// before the generic one in each directory
+fn mimeapps_list_paths() -> Vec<PathBuf> {
+    let xdg_dirs = match BaseDirectories::new() {
+        Ok(xdg_dirs) => xdg_dirs,
+        Err(_) => return Vec::new(),
+    };
+    let mut dirs = vec![xdg_dirs.get_config_home()];
+    dirs.extend(xdg_dirs.get_config_dirs());
+    dirs.push(xdg_dirs.get_data_home().join("applications"));
+    dirs.extend(xdg_dirs.get_data_dirs().into_iter().map(|dir| dir.join("applications")));
+
+    let mut file_names: Vec<String> = env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(|desktop| format!("{}-mimeapps.list", desktop.to_lowercase()))
+        .collect();
+    file_names.push("mimeapps.list".to_string());
+
+    dirs.iter()
+        .flat_map(|dir| file_names.iter().map(move |name| dir.join(name)))
+        .collect()
+}
+
+fn parse_mimeapps_list(contents: &str) -> MimeApps {
+    let mut mimeapps = MimeApps::default();
+    for (section, associations) in parse_sections(contents) {
+        let target = match section.as_str() {
+            "Default Applications" => &mut mimeapps.defaults,
+            "Added Associations" => &mut mimeapps.added,
+            "Removed Associations" => &mut mimeapps.removed,
+            _ => continue,
+        };
+        // A repeated section adds to the earlier one
+        for (mime_type, ids) in associations {
+            target.entry(mime_type).or_default().extend(ids);
+        }
+    }
+    mimeapps
+}
+
+fn mimeinfo_caches() -> &'static [HashMap<String, Vec<String>>] {
+    static CACHES: OnceLock<Vec<HashMap<String, Vec<String>>>> = OnceLock::new();
+    CACHES.get_or_init(|| {
+        let xdg_dirs = match BaseDirectories::new() {
+            Ok(xdg_dirs) => xdg_dirs,
+            Err(_) => return Vec::new(),
+        };
+        let mut data_dirs = vec![xdg_dirs.get_data_home()];
+        data_dirs.extend(xdg_dirs.get_data_dirs());
+        data_dirs.iter()
+            .filter_map(|dir| fs::read_to_string(dir.join("applications").join("mimeinfo.cache")).ok())
+            .filter_map(|contents| {
+                parse_sections(&contents).into_iter()
+                    .find(|(section, _)| section == "MIME Cache")
+                    .map(|(_, associations)| associations)
+            })
+            .collect()
+    })
+}
+
+// Each section maps MIME types to a ;-separated list of desktop file IDs
+fn parse_sections(contents: &str) -> Vec<(String, HashMap<String, Vec<String>>)> {
+    let mut sections: Vec<(String, HashMap<String, Vec<String>>)> = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push((line[1..line.len() - 1].to_string(), HashMap::new()));
+        } else if let (Some((_, associations)), Some((mime_type, ids))) = (sections.last_mut(), line.split_once('=')) {
+            let ids = ids.split(';')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string);
+            associations.entry(mime_type.trim().to_lowercase()).or_default().extend(ids);
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn ids(map: &HashMap<String, Vec<String>>, mime_type: &str) -> Vec<String> {
+        map.get(mime_type).cloned().unwrap_or_default()
+    }
+
+    #[test]
+    fn repeated_sections_are_merged() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mimeapps/mimeapps.list");
+        let mimeapps = parse_mimeapps_list(&fs::read_to_string(fixture).unwrap());
+
+        assert_eq!(ids(&mimeapps.defaults, "text/html"), ["firefox.desktop", "chromium.desktop"]);
+        assert_eq!(ids(&mimeapps.defaults, "x-scheme-handler/https"), ["firefox.desktop"]);
+        assert_eq!(ids(&mimeapps.defaults, "application/pdf"), ["org.gnome.Evince.desktop"]);
+        assert_eq!(ids(&mimeapps.added, "image/png"), ["org.gnome.Loupe.desktop", "gimp.desktop", "krita.desktop"]);
+        assert_eq!(ids(&mimeapps.added, "text/plain"), ["org.gnome.TextEditor.desktop"]);
+        assert_eq!(ids(&mimeapps.removed, "image/png"), ["gimp.desktop"]);
+    }
+
+    #[test]
+    fn keys_before_the_first_section_are_ignored() {
+        let sections = parse_sections("text/html=stray.desktop\n[MIME Cache]\ntext/html=a.desktop;b.desktop;\n");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(ids(&sections[0].1, "text/html"), ["a.desktop", "b.desktop"]);
+    }
+}
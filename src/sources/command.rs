@@ -0,0 +1,208 @@
+use crate::fuzzy::FuzzyItem;
+use crate::sources::SourceFinder;
+use crate::config::CommandSourceConfig;
+use crate::launcher::{escape_field_codes, in_terminal};
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use regex::{Captures, Regex};
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use which::which;
+
+const DEFAULT_TIMEOUT: u64 = 5;
+
+pub struct CommandFinder {
+    sources: Vec<CommandSourceConfig>,
+    terminal: Option<Vec<String>>,
+    // Commands that failed or timed out while loading
+    failures: Mutex<Vec<String>>,
+}
+
+impl SourceFinder for CommandFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        // Each command runs as its own task, so a slow one doesn't hold up the rest
+        self.sources.par_iter()
+            .flat_map(|source| self.source_entries(source))
+            .collect()
+    }
+
+    fn source_order(&self) -> usize {
+        10
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self.sources.iter()
+            .filter(|source| source.terminal && self.terminal.is_none())
+            .map(|source| format!("Commands: no terminal found, leaving out the {} source", source.name))
+            .collect();
+        warnings.append(&mut self.failures.lock().unwrap());
+        warnings
+    }
+}
+
+impl CommandFinder {
+    pub fn new(sources: Vec<CommandSourceConfig>, terminal: Option<Vec<String>>) -> Self {
+        CommandFinder { sources, terminal, failures: Mutex::new(Vec::new()) }
+    }
+
+    fn source_entries(&self, source: &CommandSourceConfig) -> Vec<FuzzyItem> {
+        let icon = source.icon.clone().unwrap_or_else(|| " ".to_string());
+        let terminal = if source.terminal {
+            match &self.terminal {
+                Some(terminal) => Some(terminal),
+                None => return Vec::new(),
+            }
+        } else {
+            None
+        };
+        let timeout = Duration::from_secs(source.timeout.unwrap_or(DEFAULT_TIMEOUT));
+        let output = match run_command(&source.command, timeout) {
+            Ok(output) => output,
+            Err(err) => {
+                self.failures.lock().unwrap().push(format!("Commands: the {} command {}", source.name, err));
+                return Vec::new();
+            }
+        };
+
+        String::from_utf8_lossy(&output)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| {
+                let fields: Vec<&str> = match &source.delimiter {
+                    Some(delimiter) => line.split(delimiter.as_str()).map(str::trim).collect(),
+                    None => vec![line.trim()],
+                };
+                let mut argv = exec_argv(&source.exec, line, &fields);
+                let executable = which(argv.first()?).ok()?;
+                argv[0] = executable.to_string_lossy().to_string();
+                let exec = argv.join(" ");
+                let escaped: Vec<String> = argv.iter().map(|arg| escape_field_codes(arg)).collect();
+                let display = fill_template(source.display.as_deref().unwrap_or("{1}"), line, &fields);
+                let (description, search_desc) = match &source.description {
+                    Some(template) => (fill_template(template, line, &fields), true),
+                    None => (source.name.clone(), false),
+                };
+                Some(FuzzyItem {
+                    display,
+                    exec: match terminal {
+                        Some(terminal) => format!("{} {}", terminal.join(" "), exec),
+                        None => exec,
+                    },
+                    argv: match terminal {
+                        Some(terminal) => in_terminal(terminal, &escaped),
+                        None => escaped,
+                    },
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description,
+                    source_path: source.command.clone(),
+                    search_desc,
+                    icon: icon.clone(),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+}
+
+/// Runs a command with `sh -c` and returns its output, killing it and
+/// anything it started when it runs longer than the timeout.
+fn run_command(command: &str, timeout: Duration) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|err| format!("failed to start: {}", err))?;
+
+    // Read while waiting, so a full pipe doesn't block the command
+    let mut stdout = child.stdout.take().ok_or("has no output")?;
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+                let _ = child.wait();
+                return Err(format!("timed out after {:?}", timeout));
+            }
+            Err(err) => return Err(format!("failed: {}", err)),
+        }
+    };
+    if !status.success() {
+        return Err(format!("failed with {}", status));
+    }
+    match reader.join() {
+        Ok(Ok(output)) => Ok(output),
+        _ => Err("output couldn't be read".to_string()),
+    }
+}
+
+// Splits the exec template into arguments before filling it in, so fields
+// with spaces stay a single argument. Arguments left empty are dropped.
+fn exec_argv(template: &str, line: &str, fields: &[&str]) -> Vec<String> {
+    template.split_whitespace()
+        .map(|arg| fill_template(arg, line, fields))
+        .filter(|arg| !arg.is_empty())
+        .collect()
+}
+
+// Replaces {0} with the whole line and {1}, {2}, ... with its fields
+fn fill_template(template: &str, line: &str, fields: &[&str]) -> String {
+    lazy_static! {
+        static ref FIELD: Regex = Regex::new(r"\{(\d+)\}").unwrap();
+    }
+    FIELD.replace_all(template, |captures: &Captures| {
+        match captures[1].parse::<usize>() {
+            Ok(0) => line.trim(),
+            Ok(index) => fields.get(index - 1).copied().unwrap_or(""),
+            Err(_) => "",
+        }
+        .to_string()
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_with_spaces_stay_one_argument() {
+        let line = "My Container|nginx:latest";
+        let fields = ["My Container", "nginx:latest"];
+        assert_eq!(
+            exec_argv("docker exec -it {1} sh", line, &fields),
+            vec!["docker", "exec", "-it", "My Container", "sh"],
+        );
+        assert_eq!(exec_argv("echo {0} {3}", line, &fields), vec!["echo", line]);
+        assert_eq!(exec_argv("open --image={2}", line, &fields), vec!["open", "--image=nginx:latest"]);
+    }
+
+    #[test]
+    fn commands_are_killed_after_the_timeout() {
+        let start = Instant::now();
+        let result = run_command("echo started; sleep 10", Duration::from_millis(200));
+        assert_eq!(result, Err("timed out after 200ms".to_string()));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn failed_commands_give_no_output() {
+        assert_eq!(run_command("printf 'a\\nb\\n'", Duration::from_secs(5)), Ok(b"a\nb\n".to_vec()));
+        assert_eq!(run_command("echo partial; exit 3", Duration::from_secs(5)), Err("failed with exit status: 3".to_string()));
+    }
+}
@@ -0,0 +1,557 @@
+use crate::fuzzy::{FuzzyItem, ItemAction, QueryProvider};
+use crate::sources::SourceFinder;
+use crate::config::{expand_home, DictionaryConfig};
+use flate2::read::GzDecoder;
+use flate2::{Decompress, FlushDecompress};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const DEFAULT_MAX_RESULTS: usize = 5;
+// Looking up every query would load the indexes on the first keystroke
+const DEFAULT_PREFIX: &str = "d ";
+const DICTD_BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub struct DictionaryFinder {
+    config: DictionaryConfig,
+}
+
+impl SourceFinder for DictionaryFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        Vec::new()
+    }
+
+    fn source_order(&self) -> usize {
+        9
+    }
+
+    fn query_providers(&self) -> Vec<Box<dyn QueryProvider>> {
+        vec![Box::new(DictionaryProvider {
+            dirs: dictionary_dirs(&self.config),
+            prefix: self.config.prefix.clone().unwrap_or_else(|| DEFAULT_PREFIX.to_string()),
+            max_results: self.config.max_results.unwrap_or(DEFAULT_MAX_RESULTS),
+            dictionaries: OnceLock::new(),
+        })]
+    }
+}
+
+/// Looks up the query as a headword in local StarDict and dictd
+/// dictionaries. The indexes are loaded on the first lookup, and only
+/// queries starting with the prefix are looked up, unless it's empty.
+struct DictionaryProvider {
+    dirs: Vec<PathBuf>,
+    prefix: String,
+    max_results: usize,
+    dictionaries: OnceLock<Vec<Dictionary>>,
+}
+
+struct Dictionary {
+    name: String,
+    format: Format,
+    entries: Vec<Entry>,
+    data: DataFile,
+}
+
+enum Format {
+    StarDict { same_type_sequence: Option<String> },
+    Dictd,
+}
+
+struct Entry {
+    word: String,
+    key: String,
+    offset: u64,
+    size: u64,
+}
+
+/// A .dict file, or a dictzip compressed .dict.dz with its chunk table.
+struct DataFile {
+    path: PathBuf,
+    compression: Compression,
+}
+
+enum Compression {
+    None,
+    // Plain gzip can't seek, so the data is inflated once and kept
+    Gzip(OnceLock<Vec<u8>>),
+    Dictzip { data_start: u64, chunk_length: u64, chunk_sizes: Vec<u64> },
+}
+
+impl QueryProvider for DictionaryProvider {
+    fn provide(&self, query: &str) -> Vec<FuzzyItem> {
+        let word = match query.strip_prefix(self.prefix.as_str()) {
+            Some(word) => word.trim(),
+            None => return Vec::new(),
+        };
+        if word.is_empty() {
+            return Vec::new();
+        }
+        let key = word.to_lowercase();
+
+        let dictionaries = self.dictionaries.get_or_init(|| load_dictionaries(&self.dirs));
+        let mut items = Vec::new();
+        for dictionary in dictionaries {
+            // Entries are sorted by key, so prefix matches are contiguous
+            let start = dictionary.entries.partition_point(|entry| entry.key.as_str() < key.as_str());
+            let matches = dictionary.entries[start..]
+                .iter()
+                .take_while(|entry| entry.key.starts_with(&key))
+                .take(self.max_results);
+
+            for entry in matches {
+                let definition = match dictionary.definition(entry) {
+                    Some(definition) => definition,
+                    None => continue,
+                };
+                let summary = definition.split_whitespace().collect::<Vec<_>>().join(" ");
+                items.push(FuzzyItem {
+                    display: format!("{} ({})", entry.word, dictionary.name),
+                    exec: entry.word.clone(),
+                    priority: 2,
+                    source_order: 9,
+                    description: summary,
+                    source_path: dictionary.data.path.to_string_lossy().to_string(),
+                    search_desc: false,
+                    icon: " ".to_string(),
+                    action: ItemAction::Copy(definition),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // Exact headword matches first, across all dictionaries
+        items.sort_by_key(|item| !item.exec.eq_ignore_ascii_case(word));
+        items.truncate(self.max_results);
+        items
+    }
+}
+
+impl Dictionary {
+    fn definition(&self, entry: &Entry) -> Option<String> {
+        let data = self.data.read(entry.offset, entry.size).ok()?;
+        let text = match &self.format {
+            Format::StarDict { same_type_sequence } => stardict_text(&data, same_type_sequence.as_deref()),
+            Format::Dictd => {
+                // dictd definitions repeat the headword on their first line
+                let text = String::from_utf8_lossy(&data).to_string();
+                match text.split_once('\n') {
+                    Some((first, rest)) if first.trim().eq_ignore_ascii_case(&entry.word) => rest.to_string(),
+                    _ => text,
+                }
+            }
+        };
+        let text = text.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+fn dictionary_dirs(config: &DictionaryConfig) -> Vec<PathBuf> {
+    if !config.paths.is_empty() {
+        return config.paths.iter().map(|path| expand_home(path)).collect();
+    }
+    let mut dirs = vec![PathBuf::from("/usr/share/stardict/dic"), PathBuf::from("/usr/share/dictd")];
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".stardict").join("dic"));
+    }
+    if let Some(data_dir) = dirs::data_dir() {
+        dirs.push(data_dir.join("stardict").join("dic"));
+    }
+    dirs
+}
+
+fn load_dictionaries(dirs: &[PathBuf]) -> Vec<Dictionary> {
+    dirs.iter()
+        .flat_map(|dir| walkdir::WalkDir::new(dir).follow_links(true))
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("ifo") => load_stardict(path),
+                Some("index") => load_dictd(path),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn load_stardict(ifo_path: &Path) -> Option<Dictionary> {
+    let ifo = fs::read_to_string(ifo_path).ok()?;
+    let mut lines = ifo.lines();
+    if lines.next()?.trim() != "StarDict's dict ifo file" {
+        return None;
+    }
+    let field = |name: &str| {
+        ifo.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .map(|value| value.trim().to_string())
+    };
+    let offset_bits = field("idxoffsetbits").and_then(|bits| bits.parse::<u32>().ok()).unwrap_or(32);
+
+    let idx = read_maybe_gzipped(&ifo_path.with_extension("idx"))?;
+    let mut entries = Vec::new();
+    let mut rest = idx.as_slice();
+    let offset_len = if offset_bits == 64 { 8 } else { 4 };
+    while let Some(nul) = rest.iter().position(|&b| b == 0) {
+        let word = String::from_utf8_lossy(&rest[..nul]).to_string();
+        rest = &rest[nul + 1..];
+        if rest.len() < offset_len + 4 {
+            break;
+        }
+        let offset = rest[..offset_len].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        let size = rest[offset_len..offset_len + 4].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        rest = &rest[offset_len + 4..];
+        entries.push(Entry { key: word.to_lowercase(), word, offset, size });
+    }
+
+    Some(Dictionary {
+        name: field("bookname").unwrap_or_else(|| file_stem(ifo_path)),
+        format: Format::StarDict { same_type_sequence: field("sametypesequence") },
+        entries: sorted(entries),
+        data: DataFile::open(&ifo_path.with_extension("dict"))?,
+    })
+}
+
+fn load_dictd(index_path: &Path) -> Option<Dictionary> {
+    let index = fs::read_to_string(index_path).ok()?;
+    let mut short_name = None;
+    let mut entries = Vec::new();
+    for line in index.lines() {
+        let mut fields = line.split('\t');
+        let (word, offset, size) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(word), Some(offset), Some(size)) => (word, offset, size),
+            _ => continue,
+        };
+        let (offset, size) = match (decode_dictd_number(offset), decode_dictd_number(size)) {
+            (Some(offset), Some(size)) => (offset, size),
+            _ => continue,
+        };
+        let entry = Entry { word: word.to_string(), key: word.to_lowercase(), offset, size };
+        // Metadata entries aren't words, but the short name makes a good title
+        if word.starts_with("00-database") || word.starts_with("00database") {
+            if word.ends_with("short") {
+                short_name = Some(entry);
+            }
+            continue;
+        }
+        entries.push(entry);
+    }
+
+    let mut dictionary = Dictionary {
+        name: file_stem(index_path),
+        format: Format::Dictd,
+        entries: sorted(entries),
+        data: DataFile::open(&index_path.with_extension("dict"))?,
+    };
+    if let Some(name) = short_name.and_then(|entry| dictionary.definition(&entry)) {
+        dictionary.name = name.lines().next().unwrap_or_default().trim().to_string();
+    }
+    Some(dictionary)
+}
+
+fn sorted(mut entries: Vec<Entry>) -> Vec<Entry> {
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    entries
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+}
+
+fn decode_dictd_number(encoded: &str) -> Option<u64> {
+    encoded.bytes().try_fold(0u64, |acc, byte| {
+        let digit = DICTD_BASE64.iter().position(|&b| b == byte)?;
+        Some(acc * 64 + digit as u64)
+    })
+}
+
+fn read_maybe_gzipped(path: &Path) -> Option<Vec<u8>> {
+    if let Ok(bytes) = fs::read(path) {
+        return Some(bytes);
+    }
+    let mut bytes = Vec::new();
+    GzDecoder::new(File::open(path.with_extension("idx.gz")).ok()?).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Extracts the readable fields of a StarDict entry. With a
+/// sametypesequence the type markers are left out of the data.
+fn stardict_text(data: &[u8], same_type_sequence: Option<&str>) -> String {
+    let mut parts = Vec::new();
+    let mut rest = data;
+    let types = same_type_sequence.map(|sequence| sequence.chars().collect::<Vec<_>>());
+    let mut index = 0;
+
+    while !rest.is_empty() {
+        let (kind, last) = match &types {
+            Some(types) => match types.get(index) {
+                Some(&kind) => (kind, index == types.len() - 1),
+                None => break,
+            },
+            None => {
+                let kind = rest[0] as char;
+                rest = &rest[1..];
+                (kind, false)
+            }
+        };
+        index += 1;
+
+        // Lowercase types are NUL terminated text, uppercase are sized blobs
+        let field = if kind.is_ascii_lowercase() {
+            let end = if last { rest.len() } else { rest.iter().position(|&b| b == 0).unwrap_or(rest.len()) };
+            let field = &rest[..end];
+            rest = rest.get(end + 1..).unwrap_or(&[]);
+            field
+        } else {
+            if last {
+                break;
+            }
+            if rest.len() < 4 {
+                break;
+            }
+            let size = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            rest = rest.get(4 + size..).unwrap_or(&[]);
+            continue;
+        };
+
+        let text = String::from_utf8_lossy(field);
+        match kind {
+            'm' | 'l' | 't' | 'y' => parts.push(text.to_string()),
+            'g' | 'h' | 'x' | 'k' | 'w' => parts.push(strip_markup(&text)),
+            _ => {}
+        }
+    }
+    parts.join("\n")
+}
+
+fn strip_markup(text: &str) -> String {
+    lazy_static! {
+        static ref BREAK: Regex = Regex::new(r"(?i)<br\s*/?>|</p>|</div>").unwrap();
+        static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+    }
+    let text = BREAK.replace_all(text, "\n");
+    TAG.replace_all(&text, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+impl DataFile {
+    fn open(path: &Path) -> Option<DataFile> {
+        if path.exists() {
+            return Some(DataFile { path: path.to_path_buf(), compression: Compression::None });
+        }
+        let path = path.with_extension("dict.dz");
+        let compression = read_dictzip_header(&path).ok()?;
+        Some(DataFile { path, compression })
+    }
+
+    fn read(&self, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        match &self.compression {
+            Compression::None => {
+                file.seek(SeekFrom::Start(offset))?;
+                let mut data = vec![0; size as usize];
+                file.read_exact(&mut data)?;
+                Ok(data)
+            }
+            Compression::Gzip(inflated) => {
+                let data = inflated.get_or_init(|| {
+                    let mut data = Vec::new();
+                    GzDecoder::new(file).read_to_end(&mut data).map(|_| data).unwrap_or_default()
+                });
+                data.get(offset as usize..(offset + size) as usize)
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+            }
+            Compression::Dictzip { data_start, chunk_length, chunk_sizes } => {
+                if size == 0 {
+                    return Ok(Vec::new());
+                }
+                // Every chunk is flushed independently, so only the ones
+                // covering the entry need to be inflated
+                let first = (offset / chunk_length) as usize;
+                let last = ((offset + size - 1) / chunk_length) as usize;
+                let mut position = data_start + chunk_sizes[..first.min(chunk_sizes.len())].iter().sum::<u64>();
+                let mut data = Vec::new();
+                for chunk_size in chunk_sizes.iter().take(last + 1).skip(first) {
+                    let mut compressed = vec![0; *chunk_size as usize];
+                    file.seek(SeekFrom::Start(position))?;
+                    file.read_exact(&mut compressed)?;
+                    position += chunk_size;
+
+                    let mut inflated = Vec::with_capacity(*chunk_length as usize);
+                    Decompress::new(false)
+                        .decompress_vec(&compressed, &mut inflated, FlushDecompress::Sync)
+                        .map_err(io::Error::other)?;
+                    data.extend_from_slice(&inflated);
+                }
+                let start = (offset - first as u64 * chunk_length) as usize;
+                let end = (start + size as usize).min(data.len());
+                Ok(data[start.min(end)..end].to_vec())
+            }
+        }
+    }
+}
+
+// Reads the gzip header, looking for the dictzip "RA" random access field
+fn read_dictzip_header(path: &Path) -> io::Result<Compression> {
+    let mut header = Vec::new();
+    File::open(path)?.take(64 * 1024).read_to_end(&mut header)?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid gzip header");
+    if header.len() < 10 || header[0] != 0x1f || header[1] != 0x8b {
+        return Err(invalid());
+    }
+    let flags = header[3];
+    let mut position = 10;
+    let mut chunks = None;
+
+    if flags & 0x04 != 0 {
+        let extra_length = u16::from_le_bytes([*header.get(position).ok_or_else(invalid)?, *header.get(position + 1).ok_or_else(invalid)?]) as usize;
+        let extra = header.get(position + 2..position + 2 + extra_length).ok_or_else(invalid)?;
+        position += 2 + extra_length;
+
+        let mut rest = extra;
+        while rest.len() >= 4 {
+            let length = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            let field = rest.get(4..4 + length).ok_or_else(invalid)?;
+            if &rest[..2] == b"RA" && field.len() >= 6 {
+                let word = |i: usize| u16::from_le_bytes([field[i], field[i + 1]]) as u64;
+                let count = word(4) as usize;
+                let sizes = (0..count)
+                    .filter(|i| 6 + i * 2 + 1 < field.len())
+                    .map(|i| word(6 + i * 2))
+                    .collect::<Vec<_>>();
+                chunks = Some((word(2), sizes));
+            }
+            rest = &rest[4 + length..];
+        }
+    }
+    // Skip the optional file name and comment, then the header CRC
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            let end = header[position..].iter().position(|&b| b == 0).ok_or_else(invalid)?;
+            position += end + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        position += 2;
+    }
+
+    Ok(match chunks {
+        Some((chunk_length, chunk_sizes)) if chunk_length > 0 => Compression::Dictzip {
+            data_start: position as u64,
+            chunk_length,
+            chunk_sizes,
+        },
+        _ => Compression::Gzip(OnceLock::new()),
+    })
+}
+
+impl DictionaryFinder {
+    pub fn new(config: DictionaryConfig) -> Self {
+        DictionaryFinder { config }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/dictionary").join(name)
+    }
+
+    fn lookup(dictionary: &Dictionary, word: &str) -> Option<String> {
+        let entry = dictionary.entries.iter().find(|entry| entry.word == word)?;
+        dictionary.definition(entry)
+    }
+
+    #[test]
+    fn dictd_numbers_are_base64() {
+        assert_eq!(decode_dictd_number("A"), Some(0));
+        assert_eq!(decode_dictd_number("n"), Some(39));
+        assert_eq!(decode_dictd_number("Bu"), Some(110));
+        assert_eq!(decode_dictd_number("B//"), Some(8191));
+        assert_eq!(decode_dictd_number("a-b"), None);
+    }
+
+    #[test]
+    fn dictd_definitions_drop_the_repeated_headword() {
+        let dictionary = load_dictd(&fixture("test.index")).unwrap();
+        assert_eq!(dictionary.name, "Test Dictionary");
+        let words: Vec<&str> = dictionary.entries.iter().map(|entry| entry.word.as_str()).collect();
+        assert_eq!(words, ["apple", "Apricot", "serendipity"]);
+        assert_eq!(lookup(&dictionary, "Apricot").as_deref(), Some("A stone fruit,\n   orange when ripe."));
+    }
+
+    #[test]
+    fn dictzip_chunks_are_read_from_the_random_access_field() {
+        match read_dictzip_header(&fixture("zipped.dict.dz")).unwrap() {
+            Compression::Dictzip { chunk_length, chunk_sizes, .. } => {
+                assert_eq!(chunk_length, 32);
+                assert_eq!(chunk_sizes.len(), 8);
+            }
+            _ => panic!("expected a dictzip header"),
+        }
+
+        // The last definition spans several chunks
+        let plain = load_dictd(&fixture("test.index")).unwrap();
+        let zipped = load_dictd(&fixture("zipped.index")).unwrap();
+        for word in ["apple", "Apricot", "serendipity"] {
+            assert!(lookup(&zipped, word).is_some());
+            assert_eq!(lookup(&zipped, word), lookup(&plain, word));
+        }
+    }
+
+    #[test]
+    fn gzip_without_random_access_is_inflated_once() {
+        let dictionary = load_dictd(&fixture("gzipped.index")).unwrap();
+        assert!(matches!(dictionary.data.compression, Compression::Gzip(_)));
+        assert_eq!(lookup(&dictionary, "apple").as_deref(), Some("A round fruit."));
+        assert_eq!(lookup(&dictionary, "serendipity").unwrap().split_whitespace().next(), Some("Finding"));
+        match &dictionary.data.compression {
+            Compression::Gzip(inflated) => assert_eq!(inflated.get().map(Vec::len), Some(250)),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn stardict_same_type_sequence_has_no_type_markers() {
+        let dictionary = load_stardict(&fixture("sts.ifo")).unwrap();
+        assert_eq!(dictionary.name, "Animals");
+        assert_eq!(lookup(&dictionary, "cat").as_deref(), Some("kat\nA small domesticated carnivore."));
+        assert_eq!(lookup(&dictionary, "Dog").as_deref(), Some("dog\nA domesticated canine."));
+    }
+
+    #[test]
+    fn stardict_fields_are_typed_without_a_sequence() {
+        let dictionary = load_stardict(&fixture("typed.ifo")).unwrap();
+        assert_eq!(dictionary.name, "typed");
+        assert_eq!(lookup(&dictionary, "markup").as_deref(), Some("Bold and & more\nsecond line\nplain meaning"));
+    }
+
+    #[test]
+    fn only_prefixed_queries_are_looked_up() {
+        let provider = DictionaryProvider {
+            dirs: vec![fixture("")],
+            prefix: DEFAULT_PREFIX.to_string(),
+            max_results: DEFAULT_MAX_RESULTS,
+            dictionaries: OnceLock::new(),
+        };
+        assert!(provider.provide("apple").is_empty());
+        assert!(provider.dictionaries.get().is_none());
+
+        let items = provider.provide("d ap");
+        // Each of the three copies matches both words, in walk order
+        let words: Vec<&str> = items.iter().map(|item| item.exec.as_str()).collect();
+        assert_eq!(words.len(), DEFAULT_MAX_RESULTS);
+        assert!(words.iter().all(|word| ["apple", "Apricot"].contains(word)));
+        assert_eq!(provider.provide("d apricot")[0].exec, "Apricot");
+    }
+}
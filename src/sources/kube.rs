@@ -0,0 +1,180 @@
+use crate::fuzzy::FuzzyItem;
+use crate::sources::SourceFinder;
+use crate::config::KubeConfig;
+use crate::launcher::{escape_field_codes, in_terminal, quote_argv};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use which::which;
+
+pub struct KubeFinder {
+    config: KubeConfig,
+    terminal: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+struct Kubeconfig {
+    #[serde(default)]
+    contexts: Vec<NamedContext>,
+    #[serde(rename = "current-context", default)]
+    current_context: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NamedContext {
+    name: String,
+    #[serde(default)]
+    context: Context,
+}
+
+#[derive(Deserialize, Default)]
+struct Context {
+    #[serde(default)]
+    cluster: String,
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
+impl SourceFinder for KubeFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let icon = "󱃾 ".to_string();
+        let kubectl = match which("kubectl") {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => return Vec::new(),
+        };
+        let k9s = if self.config.k9s {
+            which("k9s").ok().zip(self.terminal.as_ref())
+        } else {
+            None
+        };
+
+        // Like kubectl, the first file in KUBECONFIG to define a context wins
+        let mut seen = HashSet::new();
+        let mut current_context = None;
+        let mut contexts = Vec::new();
+        for path in kubeconfig_paths() {
+            let kubeconfig = match load_kubeconfig(&path) {
+                Some(kubeconfig) => kubeconfig,
+                None => continue,
+            };
+            if current_context.is_none() {
+                current_context = kubeconfig.current_context.clone();
+            }
+            for named in kubeconfig.contexts {
+                if seen.insert(named.name.clone()) {
+                    contexts.push((named, path.to_string_lossy().to_string()));
+                }
+            }
+        }
+
+        // Namespaces to offer for each cluster: the configured ones and any
+        // that another context on the same cluster points at
+        let mut cluster_namespaces: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+        for (named, _) in &contexts {
+            let namespaces = cluster_namespaces.entry(named.context.cluster.as_str()).or_default();
+            namespaces.extend(self.config.namespaces.iter().map(String::as_str));
+            namespaces.extend(named.context.namespace.as_deref());
+        }
+
+        let mut items = Vec::new();
+        for (named, source_path) in &contexts {
+            let namespace = named.context.namespace.as_deref().unwrap_or("default");
+            let description = format!("Cluster: {}, namespace: {}", named.context.cluster, namespace);
+
+            let argv = vec![kubectl.clone(), "config".to_string(), "use-context".to_string(), named.name.clone()];
+            items.push(FuzzyItem {
+                display: named.name.clone(),
+                exec: argv.join(" "),
+                argv: escaped(&argv),
+                priority: 2,
+                source_order: self.source_order(),
+                description: description.clone(),
+                source_path: source_path.clone(),
+                search_desc: true,
+                icon: icon.clone(),
+                ..Default::default()
+            });
+
+            if let Some((k9s, terminal)) = &k9s {
+                let argv = vec![k9s.to_string_lossy().to_string(), "--context".to_string(), named.name.clone()];
+                items.push(FuzzyItem {
+                    display: format!("{} (k9s)", named.name),
+                    exec: format!("{} {}", terminal.join(" "), argv.join(" ")),
+                    argv: in_terminal(terminal, &escaped(&argv)),
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description: description.clone(),
+                    source_path: source_path.clone(),
+                    search_desc: true,
+                    icon: icon.clone(),
+                    ..Default::default()
+                });
+            }
+
+            let others = cluster_namespaces[named.context.cluster.as_str()].iter()
+                .filter(|other| **other != namespace);
+            for other in others {
+                let script = format!(
+                    "{0} config use-context \"$1\" && {0} config set-context --current --namespace=\"$2\"",
+                    kubectl,
+                );
+                // Two kubectl calls, so the context and namespace are passed
+                // to a shell as arguments rather than pasted into the script
+                let argv: Vec<String> = ["sh", "-c", &script, "sh", &named.name, other].iter()
+                    .map(|arg| arg.to_string())
+                    .collect();
+                items.push(FuzzyItem {
+                    display: format!("{}/{}", named.name, other),
+                    exec: quote_argv(&argv),
+                    argv: escaped(&argv),
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description: format!("Cluster: {}, namespace: {}", named.context.cluster, other),
+                    source_path: source_path.clone(),
+                    search_desc: true,
+                    icon: icon.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // Mark the active context so it stands out in the description line
+        if let Some(current) = current_context {
+            for item in items.iter_mut().filter(|item| item.display == current) {
+                item.description.push_str(" (current)");
+            }
+        }
+
+        items
+    }
+
+    fn source_order(&self) -> usize {
+        2
+    }
+}
+
+fn escaped(argv: &[String]) -> Vec<String> {
+    argv.iter().map(|arg| escape_field_codes(arg)).collect()
+}
+
+fn kubeconfig_paths() -> Vec<PathBuf> {
+    match env::var_os("KUBECONFIG") {
+        Some(paths) if !paths.is_empty() => env::split_paths(&paths).collect(),
+        _ => dirs::home_dir()
+            .map(|home| vec![home.join(".kube").join("config")])
+            .unwrap_or_default(),
+    }
+}
+
+fn load_kubeconfig(path: &Path) -> Option<Kubeconfig> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_yaml::from_str(&contents).ok()
+}
+
+impl KubeFinder {
+    pub fn new(config: KubeConfig, terminal: Option<Vec<String>>) -> Self {
+        KubeFinder { config, terminal }
+    }
+}
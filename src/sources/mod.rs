@@ -1,22 +1,69 @@
 mod xdg_desktop;
 mod path;
+mod kube;
+mod steam;
+mod places;
+mod mounts;
+mod projects;
+mod notes;
+mod time;
+mod dictionary;
+mod command;
+mod units;
+mod snippets;
 
-pub use xdg_desktop::XdgDesktopFinder;
+pub use xdg_desktop::{XdgDesktopFinder, check_desktop_files};
+pub(crate) use xdg_desktop::{directory_entry, handlers, open_argv, preferred_terminal};
 pub use path::PathFinder;
-use crate::fuzzy::FuzzyItem;
+pub use kube::KubeFinder;
+pub use steam::SteamFinder;
+pub use places::PlacesFinder;
+pub use mounts::MountsFinder;
+pub use projects::ProjectsFinder;
+pub use notes::NotesFinder;
+pub use time::TimeFinder;
+pub use dictionary::DictionaryFinder;
+pub use command::CommandFinder;
+pub use units::UnitsFinder;
+pub use snippets::{SnippetsFinder, expand_snippet};
+use crate::fuzzy::{FuzzyItem, QueryProvider};
+use crate::config::Config;
+use crate::launcher::terminal_command;
 use clap::ValueEnum;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 pub enum Source {
     Desktop,
     Path,
+    Kube,
+    Steam,
+    Places,
+    Mounts,
+    Projects,
+    Notes,
+    Time,
+    Dict,
+    Commands,
+    Units,
+    Snippets,
 }
 
 impl Source {
-    pub fn get_finder(&self) -> Box<dyn SourceFinder> {
+    pub fn get_finder(&self, config: &Config) -> Box<dyn SourceFinder> {
         match self {
-            Source::Desktop => Box::new(XdgDesktopFinder::new()),
+            Source::Desktop => Box::new(XdgDesktopFinder::new(config.desktop.clone(), terminal_command(&config.terminal), config.ui.nested_actions, config.debug)),
             Source::Path => Box::new(PathFinder::new()),
+            Source::Kube => Box::new(KubeFinder::new(config.kube.clone(), terminal_command(&config.terminal))),
+            Source::Steam => Box::new(SteamFinder::new()),
+            Source::Places => Box::new(PlacesFinder::new(config.places.clone())),
+            Source::Mounts => Box::new(MountsFinder::new(config.places.clone())),
+            Source::Projects => Box::new(ProjectsFinder::new()),
+            Source::Notes => Box::new(NotesFinder::new(config.notes.clone(), terminal_command(&config.terminal))),
+            Source::Time => Box::new(TimeFinder),
+            Source::Dict => Box::new(DictionaryFinder::new(config.dictionary.clone())),
+            Source::Commands => Box::new(CommandFinder::new(config.sources.clone(), terminal_command(&config.terminal))),
+            Source::Units => Box::new(UnitsFinder::new(terminal_command(&config.terminal), config.systemd_run.parameters.iter().any(|param| param == "--user"))),
+            Source::Snippets => Box::new(SnippetsFinder::new(config.snippets.clone())),
         }
     }
 }
@@ -24,10 +71,15 @@ impl Source {
 pub trait SourceFinder: Send + Sync {
     fn find_entries(&self) -> Vec<FuzzyItem>;
     fn source_order(&self) -> usize;
-}
 
-// Implement Send and Sync for XdgDesktopFinder and PathFinder
-unsafe impl Send for XdgDesktopFinder {}
-unsafe impl Sync for XdgDesktopFinder {}
-unsafe impl Send for PathFinder {}
-unsafe impl Sync for PathFinder {}
\ No newline at end of file
+    /// Providers that generate items from the query at search time.
+    fn query_providers(&self) -> Vec<Box<dyn QueryProvider>> {
+        Vec::new()
+    }
+
+    /// Problems that left items out, shown in debug mode. Called after
+    /// `find_entries`.
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
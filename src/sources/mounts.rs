@@ -0,0 +1,303 @@
+use crate::fuzzy::{FuzzyItem, ItemAction};
+use crate::sources::SourceFinder;
+use crate::config::PlacesConfig;
+use crate::launcher::{escape_field_codes, resolve_argv};
+use crate::uri::path_to_uri;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use which::which;
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+const DISK_BY_LABEL: &str = "/dev/disk/by-label";
+
+// Filesystems that never back a folder worth opening
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs",
+    "devpts", "devtmpfs", "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs",
+    "overlay", "proc", "pstore", "ramfs", "rpc_pipefs", "securityfs", "squashfs",
+    "sysfs", "tmpfs", "tracefs", "fuse.portal", "fuse.gvfsd-fuse",
+];
+
+// Network filesystems, where statvfs can hang on an unreachable server.
+// FUSE filesystems are skipped as well, since sshfs and the like are too.
+const REMOTE_FILESYSTEMS: &[&str] = &[
+    "9p", "afs", "ceph", "cifs", "davfs", "glusterfs", "ncpfs", "nfs", "nfs4",
+    "smb3", "smbfs", "sshfs",
+];
+
+pub struct MountsFinder {
+    config: PlacesConfig,
+}
+
+/// A single line of /proc/self/mountinfo.
+struct MountInfo {
+    mount_point: PathBuf,
+    fs_type: String,
+    device: String,
+}
+
+impl SourceFinder for MountsFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let icon = " ".to_string();
+        let mounts = match fs::read_to_string(MOUNTINFO) {
+            Ok(contents) => parse_mountinfo(&contents),
+            Err(_) => return Vec::new(),
+        };
+        let labels = device_labels(Path::new(DISK_BY_LABEL));
+        let mut items = Vec::new();
+
+        // A configured file manager opens every mount, otherwise the
+        // default application is looked up when one is opened
+        let file_manager = match &self.config.file_manager {
+            Some(command) => resolve_argv(command),
+            None => None,
+        };
+        for mount in &mounts {
+            let uri = path_to_uri(&mount.mount_point);
+            let (exec, argv, action) = match (&self.config.file_manager, &file_manager) {
+                (_, Some(file_manager)) => (
+                    format!("{} {}", file_manager.join(" "), uri),
+                    file_manager.iter().chain([&uri]).map(|arg| escape_field_codes(arg)).collect(),
+                    ItemAction::Launch,
+                ),
+                // The configured file manager isn't installed
+                (Some(_), None) => break,
+                (None, None) => (uri.clone(), Vec::new(), ItemAction::OpenDefault(uri.clone())),
+            };
+
+            let canonical_device = canonical_device(&mount.device);
+            let label = labels.get(&canonical_device).cloned();
+            let mut description = format!("{} · {}", mount.device, mount.fs_type);
+            if !is_remote(&mount.fs_type) {
+                if let Some(free) = free_space(&mount.mount_point) {
+                    description.push_str(&format!(" · {} free", format_size(free)));
+                }
+            }
+
+            items.push(FuzzyItem {
+                display: label.unwrap_or_else(|| mount.mount_point.to_string_lossy().to_string()),
+                exec,
+                argv,
+                action,
+                target: Some(uri),
+                priority: 2,
+                source_order: self.source_order(),
+                description,
+                source_path: mount.mount_point.to_string_lossy().to_string(),
+                search_desc: true,
+                icon: icon.clone(),
+                ..Default::default()
+            });
+        }
+
+        // Labeled removable devices that aren't mounted get a mount action
+        if let Ok(udisksctl) = which("udisksctl") {
+            let mounted: Vec<PathBuf> = mounts.iter().map(|mount| canonical_device(&mount.device)).collect();
+            for (device, label) in &labels {
+                if mounted.contains(device) || !is_removable(device) {
+                    continue;
+                }
+                let fs_type = udev_fs_type(device).unwrap_or_else(|| "unknown".to_string());
+                let argv = [udisksctl.as_os_str(), "mount".as_ref(), "-b".as_ref(), device.as_os_str()]
+                    .map(|arg| arg.to_string_lossy().to_string());
+                items.push(FuzzyItem {
+                    display: format!("{} (Mount)", label),
+                    exec: argv.join(" "),
+                    argv: argv.iter().map(|arg| escape_field_codes(arg)).collect(),
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description: format!("{} · {} · not mounted", device.to_string_lossy(), fs_type),
+                    source_path: device.to_string_lossy().to_string(),
+                    search_desc: true,
+                    icon: icon.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        items
+    }
+
+    fn source_order(&self) -> usize {
+        5
+    }
+}
+
+/// Parses the contents of a mountinfo file, skipping pseudo filesystems
+/// and bind mounts of a directory that is already mounted elsewhere.
+fn parse_mountinfo(contents: &str) -> Vec<MountInfo> {
+    let mut seen = HashSet::new();
+    contents.lines()
+        .filter_map(|line| {
+            // Optional fields end with a lone "-", followed by type and source
+            let (mount_fields, fs_fields) = line.split_once(" - ")?;
+            let mut mount_fields = mount_fields.split(' ').skip(2);
+            let (device_id, root, mount_point) = (mount_fields.next()?, mount_fields.next()?, mount_fields.next()?);
+            let mut fs_fields = fs_fields.split(' ');
+            let fs_type = fs_fields.next()?;
+            let device = fs_fields.next()?;
+
+            // The first mount of a device's directory is the original one
+            if PSEUDO_FILESYSTEMS.contains(&fs_type) || !seen.insert((device_id, root)) {
+                return None;
+            }
+            Some(MountInfo {
+                mount_point: PathBuf::from(unescape_octal(mount_point)),
+                fs_type: fs_type.to_string(),
+                device: unescape_octal(device),
+            })
+        })
+        .collect()
+}
+
+// The kernel escapes space, tab, newline and backslash as \ooo
+fn unescape_octal(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            let octal = digits.len() == 3 && digits.chars().all(|digit| ('0'..='7').contains(&digit));
+            if let Some(byte) = octal.then(|| u8::from_str_radix(&digits, 8).ok()).flatten() {
+                output.push(byte as char);
+                chars.nth(2);
+                continue;
+            }
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// Maps each labeled block device to its label.
+fn device_labels(dir: &Path) -> HashMap<PathBuf, String> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let device = fs::canonicalize(entry.path()).ok()?;
+            let label = unescape_udev(&entry.file_name().to_string_lossy());
+            Some((device, label))
+        })
+        .collect()
+}
+
+// udev encodes unsafe characters in link names as \xNN
+fn unescape_udev(input: &str) -> String {
+    let mut bytes = Vec::with_capacity(input.len());
+    let raw = input.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\' && raw.get(i + 1) == Some(&b'x') && i + 3 < raw.len() {
+            let hex = std::str::from_utf8(&raw[i + 2..i + 4]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                bytes.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+fn canonical_device(device: &str) -> PathBuf {
+    fs::canonicalize(device).unwrap_or_else(|_| PathBuf::from(device))
+}
+
+// Partitions inherit the removable flag of the disk they belong to
+fn is_removable(device: &Path) -> bool {
+    let name = match device.file_name() {
+        Some(name) => name,
+        None => return false,
+    };
+    let sys_path = match fs::canonicalize(Path::new("/sys/class/block").join(name)) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+    let removable = |path: &Path| fs::read_to_string(path.join("removable")).is_ok_and(|flag| flag.trim() == "1");
+    removable(&sys_path) || sys_path.parent().is_some_and(removable)
+}
+
+fn udev_fs_type(device: &Path) -> Option<String> {
+    let dev = fs::read_to_string(Path::new("/sys/class/block").join(device.file_name()?).join("dev")).ok()?;
+    let data = fs::read_to_string(format!("/run/udev/data/b{}", dev.trim())).ok()?;
+    data.lines()
+        .find_map(|line| line.strip_prefix("E:ID_FS_TYPE="))
+        .map(String::from)
+}
+
+fn is_remote(fs_type: &str) -> bool {
+    REMOTE_FILESYSTEMS.contains(&fs_type) || fs_type == "fuse" || fs_type.starts_with("fuse.")
+}
+
+fn free_space(path: &Path) -> Option<u64> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+impl MountsFinder {
+    pub fn new(config: PlacesConfig) -> Self {
+        MountsFinder { config }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mountinfo_unescapes_and_skips_pseudo_filesystems() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mounts/mountinfo");
+        let mounts = parse_mountinfo(&fs::read_to_string(fixture).unwrap());
+        let parsed: Vec<(&Path, &str, &str)> = mounts.iter()
+            .map(|mount| (mount.mount_point.as_path(), mount.fs_type.as_str(), mount.device.as_str()))
+            .collect();
+        assert_eq!(parsed, vec![
+            (Path::new("/"), "ext4", "/dev/nvme0n1p2"),
+            (Path::new("/boot"), "vfat", "/dev/nvme0n1p1"),
+            (Path::new("/run/media/user/My Drive"), "exfat", "/dev/sdb1"),
+            (Path::new("/mnt/tab\tand\\backslash"), "ext4", "/dev/sdc1"),
+            (Path::new("/mnt/nas"), "cifs", "//nas/My Share"),
+            (Path::new("/srv/shared"), "ext4", "/dev/nvme0n1p2"),
+            (Path::new("/home/user/remote"), "fuse.sshfs", "user@host:/home/user"),
+        ]);
+    }
+
+    #[test]
+    fn remote_filesystems_are_not_queried() {
+        assert!(is_remote("cifs"));
+        assert!(is_remote("nfs4"));
+        assert!(is_remote("fuse.sshfs"));
+        assert!(!is_remote("ext4"));
+        assert!(!is_remote("fuseblk"));
+    }
+
+    #[test]
+    fn octal_escapes_need_three_digits() {
+        assert_eq!(unescape_octal("a\\040b"), "a b");
+        assert_eq!(unescape_octal("a\\04"), "a\\04");
+        assert_eq!(unescape_octal("a\\+12b"), "a\\+12b");
+        assert_eq!(unescape_octal("a\\999"), "a\\999");
+    }
+}
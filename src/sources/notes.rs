@@ -0,0 +1,281 @@
+use crate::fuzzy::{FuzzyItem, QueryProvider};
+use crate::sources::SourceFinder;
+use crate::config::{expand_home, NotesConfig};
+use crate::launcher::{escape_field_codes, resolve_argv};
+use rayon::prelude::*;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+pub struct NotesFinder {
+    directory: Option<PathBuf>,
+    editor: Option<Vec<String>>,
+    warning: Option<String>,
+}
+
+struct Note {
+    title: Option<String>,
+    summary: String,
+    tags: Vec<String>,
+}
+
+impl SourceFinder for NotesFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let icon = " ".to_string();
+        let (directory, editor) = match (&self.directory, &self.editor) {
+            (Some(directory), Some(editor)) => (directory, editor),
+            _ => return Vec::new(),
+        };
+
+        WalkDir::new(directory)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "md" || ext == "markdown"))
+            .par_bridge()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let note = parse_note(&fs::read_to_string(path).ok()?);
+                let title = note.title.unwrap_or_else(|| {
+                    path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+                });
+                // Tags follow the summary, so both are searched
+                let tags: Vec<String> = note.tags.iter().map(|tag| format!("#{}", tag)).collect();
+                let summary = [note.summary, tags.join(" ")].into_iter()
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let (description, search_desc) = if summary.is_empty() {
+                    ("No description".to_string(), false)
+                } else {
+                    (summary, true)
+                };
+                Some(FuzzyItem {
+                    display: title,
+                    exec: format!("{} {}", editor.join(" "), path.to_string_lossy()),
+                    argv: edit_argv(editor, path),
+                    target: Some(path.to_string_lossy().to_string()),
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description,
+                    source_path: path.to_string_lossy().to_string(),
+                    search_desc,
+                    icon: icon.clone(),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    fn source_order(&self) -> usize {
+        7
+    }
+
+    fn query_providers(&self) -> Vec<Box<dyn QueryProvider>> {
+        match (&self.directory, &self.editor) {
+            (Some(directory), Some(editor)) => vec![Box::new(NewNoteProvider {
+                directory: directory.clone(),
+                editor: editor.clone(),
+            })],
+            _ => Vec::new(),
+        }
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        self.warning.iter().cloned().collect()
+    }
+}
+
+/// Offers to create a note named after the query when nothing matches it.
+struct NewNoteProvider {
+    directory: PathBuf,
+    editor: Vec<String>,
+}
+
+impl QueryProvider for NewNoteProvider {
+    fn provide(&self, query: &str) -> Vec<FuzzyItem> {
+        let file_name = note_file_name(query);
+        if file_name.is_empty() {
+            return Vec::new();
+        }
+        let path = self.directory.join(format!("{}.md", file_name));
+        vec![FuzzyItem {
+            display: format!("{} (New note)", query.trim()),
+            exec: format!("{} {}", self.editor.join(" "), path.to_string_lossy()),
+            argv: edit_argv(&self.editor, &path),
+            priority: 2,
+            source_order: 7,
+            description: path.to_string_lossy().to_string(),
+            source_path: path.to_string_lossy().to_string(),
+            search_desc: false,
+            icon: " ".to_string(),
+            ..Default::default()
+        }]
+    }
+
+    fn is_fallback(&self) -> bool {
+        true
+    }
+}
+
+// Lowercase, with runs of anything but letters and digits turned into "-"
+fn note_file_name(query: &str) -> String {
+    query.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Extracts the first heading, the first paragraph and the front matter
+/// tags from a Markdown note.
+fn parse_note(contents: &str) -> Note {
+    let mut tags = Vec::new();
+    let mut front_title = None;
+    let mut body = contents;
+
+    if let Some((front_matter, rest)) = split_front_matter(contents) {
+        if let Ok(front_matter) = serde_yaml::from_str::<serde_yaml::Value>(front_matter) {
+            tags = front_matter_tags(&front_matter);
+            front_title = front_matter.get("title").and_then(|title| title.as_str()).map(String::from);
+        }
+        body = rest;
+    }
+
+    let mut title = None;
+    let mut summary = Vec::new();
+    let mut summary_done = false;
+    let mut in_code_block = false;
+    for line in body.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let heading = line.trim_start_matches('#');
+        if heading.len() < line.len() && heading.starts_with(' ') {
+            if title.is_none() {
+                title = Some(heading.trim().to_string());
+            }
+            summary_done |= !summary.is_empty();
+        } else if line.is_empty() {
+            summary_done |= !summary.is_empty();
+        } else if !summary_done {
+            summary.push(line);
+        }
+        if title.is_some() && summary_done {
+            break;
+        }
+    }
+
+    Note {
+        title: title.or(front_title),
+        summary: summary.join(" "),
+        tags,
+    }
+}
+
+// Front matter sits between two "---" lines at the very start of a note,
+// with LF or CRLF line endings
+fn split_front_matter(contents: &str) -> Option<(&str, &str)> {
+    let rest = contents.strip_prefix("---\n").or_else(|| contents.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']) == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+// Tags may be a YAML list or a comma or space separated string
+fn front_matter_tags(front_matter: &serde_yaml::Value) -> Vec<String> {
+    match front_matter.get("tags") {
+        Some(serde_yaml::Value::Sequence(tags)) => tags.iter()
+            .filter_map(|tag| tag.as_str())
+            .map(String::from)
+            .collect(),
+        Some(serde_yaml::Value::String(tags)) => tags
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The argv that opens a note in the editor, which includes the terminal.
+fn edit_argv(editor: &[String], path: &Path) -> Vec<String> {
+    editor.iter()
+        .map(|arg| escape_field_codes(arg))
+        .chain([escape_field_codes(&path.to_string_lossy())])
+        .collect()
+}
+
+impl NotesFinder {
+    pub fn new(config: NotesConfig, terminal: Option<Vec<String>>) -> Self {
+        let directory = config.directory.as_deref().map(expand_home);
+        let editor = config.editor
+            .or_else(|| env::var("VISUAL").ok())
+            .or_else(|| env::var("EDITOR").ok())
+            .unwrap_or_else(|| "vi".to_string());
+        // The editor runs in a terminal, so notes are left out without one
+        let (editor, warning) = match (resolve_argv(&editor), terminal) {
+            (Some(editor), Some(terminal)) => (Some([terminal, editor].concat()), None),
+            (Some(_), None) => (None, Some("Notes: no terminal found to run the editor in".to_string())),
+            (None, _) => (None, Some(format!("Notes: editor not found: {}", editor))),
+        };
+        let warning = warning.filter(|_| directory.is_some());
+        NotesFinder { directory, editor, warning }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_note(name: &str) -> Note {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/notes").join(name);
+        parse_note(&fs::read_to_string(fixture).unwrap())
+    }
+
+    #[test]
+    fn title_and_summary_come_from_the_first_heading_and_paragraph() {
+        let note = fixture_note("plain.md");
+        assert_eq!(note.title.as_deref(), Some("Shopping list"));
+        assert_eq!(note.summary, "Things to buy on Saturday, before the market closes.");
+        assert!(note.tags.is_empty());
+    }
+
+    #[test]
+    fn front_matter_gives_tags_and_a_fallback_title() {
+        let note = fixture_note("front-matter.md");
+        assert_eq!(note.title.as_deref(), Some("Trip to Lisbon"));
+        assert_eq!(note.summary, "Flights are booked.");
+        assert_eq!(note.tags, ["travel", "2024"]);
+
+        let note = fixture_note("string-tags.md");
+        assert_eq!(note.title.as_deref(), Some("Reading list"));
+        assert_eq!(note.tags, ["books", "later", "fiction"]);
+    }
+
+    #[test]
+    fn front_matter_with_crlf_line_endings() {
+        let note = parse_note("---\r\ntitle: Meeting\r\ntags: [work]\r\n---\r\nAgenda for Monday.\r\n");
+        assert_eq!(note.title.as_deref(), Some("Meeting"));
+        assert_eq!(note.summary, "Agenda for Monday.");
+        assert_eq!(note.tags, ["work"]);
+    }
+
+    #[test]
+    fn unterminated_front_matter_is_part_of_the_body() {
+        let note = parse_note("---\ntitle: Draft\n");
+        assert_eq!(note.title, None);
+        assert_eq!(note.summary, "--- title: Draft");
+    }
+}
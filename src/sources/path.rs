@@ -2,12 +2,14 @@ use std::env;
 use which::which;
 use crate::fuzzy::FuzzyItem;
 use crate::sources::SourceFinder;
+use crate::launcher::escape_field_codes;
 use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 
+#[derive(Default)]
 pub struct PathFinder;
 
 impl SourceFinder for PathFinder {
@@ -48,12 +50,14 @@ impl SourceFinder for PathFinder {
                             Some(FuzzyItem {
                                 display: name,
                                 exec: format!("\"{}\"", path.to_str().unwrap()),
+                                argv: vec![escape_field_codes(path.to_str().unwrap())],
                                 priority: 1,
                                 source_order: 1,
                                 description: "Executable in PATH".to_string(),
                                 source_path: path.to_str().unwrap().to_string(),
                                 search_desc: false,
                                 icon: icon.clone(), // Add this line
+                                ..Default::default()
                             })
                         } else {
                             None
@@ -0,0 +1,189 @@
+use crate::fuzzy::{FuzzyItem, ItemAction};
+use crate::sources::SourceFinder;
+use crate::config::PlacesConfig;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::launcher::{escape_field_codes, resolve_argv};
+use crate::uri::{path_to_uri, percent_decode, uri_to_path};
+
+pub struct PlacesFinder {
+    config: PlacesConfig,
+}
+
+impl SourceFinder for PlacesFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let icon = " ".to_string();
+        // A configured file manager opens every place, otherwise the
+        // default application is looked up when one is opened
+        let file_manager = match &self.config.file_manager {
+            Some(command) => match resolve_argv(command) {
+                Some(argv) => Some(argv),
+                None => return Vec::new(),
+            },
+            None => None,
+        };
+        let home = match dirs::home_dir() {
+            Some(home) => home,
+            None => return Vec::new(),
+        };
+        let config_home = dirs::config_dir().unwrap_or_else(|| home.join(".config"));
+
+        let mut places = vec![Place {
+            label: "Home".to_string(),
+            uri: path_to_uri(&home),
+            source_path: home.to_string_lossy().to_string(),
+        }];
+        places.extend(user_dirs(&config_home.join("user-dirs.dirs"), &home));
+        places.extend(gtk_bookmarks(&config_home.join("gtk-3.0").join("bookmarks")));
+
+        let mut seen = HashSet::new();
+        places.into_iter()
+            .filter(|place| seen.insert(place.uri.clone()))
+            .map(|place| {
+                let (exec, argv, action) = match &file_manager {
+                    Some(file_manager) => (
+                        format!("{} {}", file_manager.join(" "), place.uri),
+                        file_manager.iter().chain([&place.uri]).map(|arg| escape_field_codes(arg)).collect(),
+                        ItemAction::Launch,
+                    ),
+                    None => (place.uri.clone(), Vec::new(), ItemAction::OpenDefault(place.uri.clone())),
+                };
+                FuzzyItem {
+                    display: place.label,
+                    exec,
+                    argv,
+                    action,
+                    target: Some(place.uri.clone()),
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description: uri_to_path(&place.uri)
+                        .map(|path| path.to_string_lossy().to_string())
+                        .unwrap_or_else(|| place.uri.clone()),
+                    source_path: place.source_path,
+                    search_desc: true,
+                    icon: icon.clone(),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn source_order(&self) -> usize {
+        4
+    }
+}
+
+struct Place {
+    label: String,
+    uri: String,
+    source_path: String,
+}
+
+/// Reads the XDG_*_DIR entries from user-dirs.dirs. Directories set to the
+/// home directory itself are disabled according to xdg-user-dirs.
+fn user_dirs(path: &Path, home: &Path) -> Vec<Place> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            if !key.starts_with("XDG_") || !key.ends_with("_DIR") {
+                return None;
+            }
+            let value = value.trim().trim_matches('"');
+            let dir = match value.strip_prefix("$HOME") {
+                Some(rest) => home.join(rest.trim_start_matches('/')),
+                None if value.starts_with('/') => PathBuf::from(value),
+                None => return None,
+            };
+            if dir == home {
+                return None;
+            }
+            Some(Place {
+                label: dir.file_name()?.to_string_lossy().to_string(),
+                uri: path_to_uri(&dir),
+                source_path: path.to_string_lossy().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads GTK bookmarks, one URI per line optionally followed by a label.
+fn gtk_bookmarks(path: &Path) -> Vec<Place> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+
+    contents.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (uri, label) = match line.split_once(' ') {
+                Some((uri, label)) => (uri, Some(label.trim())),
+                None => (line.trim(), None),
+            };
+            let label = match label {
+                Some(label) if !label.is_empty() => label.to_string(),
+                _ => uri_label(uri),
+            };
+            Place {
+                label,
+                uri: uri.to_string(),
+                source_path: path.to_string_lossy().to_string(),
+            }
+        })
+        .collect()
+}
+
+// Falls back to the last path segment, or the host for remote roots
+fn uri_label(uri: &str) -> String {
+    let trimmed = uri.trim_end_matches('/');
+    let last = trimmed.rsplit('/').next().unwrap_or(trimmed);
+    percent_decode(last)
+}
+
+impl PlacesFinder {
+    pub fn new(config: PlacesConfig) -> Self {
+        PlacesFinder { config }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/places").join(name)
+    }
+
+    #[test]
+    fn user_dirs_are_read_relative_to_home() {
+        let places = user_dirs(&fixture("user-dirs.dirs"), Path::new("/home/user"));
+        let places: Vec<(&str, &str)> = places.iter().map(|place| (place.label.as_str(), place.uri.as_str())).collect();
+        assert_eq!(places, [
+            ("Desktop", "file:///home/user/Desktop"),
+            ("Downloads", "file:///home/user/Downloads"),
+            ("Documents", "file:///data/Documents"),
+            ("Pictures", "file:///home/user/Pictures"),
+        ]);
+    }
+
+    #[test]
+    fn bookmarks_are_labeled_or_named_after_their_uri() {
+        let places = gtk_bookmarks(&fixture("bookmarks"));
+        let places: Vec<(&str, &str)> = places.iter().map(|place| (place.label.as_str(), place.uri.as_str())).collect();
+        assert_eq!(places, [
+            ("fuzzyd", "file:///home/user/src/fuzzyd"),
+            ("My Projects", "file:///home/user/My%20Projects"),
+            ("server.example.com", "sftp://server.example.com/"),
+            ("notes", "file:///home/user/notes"),
+            ("trailing space", "file:///home/user/trailing%20space"),
+        ]);
+    }
+}
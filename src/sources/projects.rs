@@ -0,0 +1,325 @@
+use crate::fuzzy::FuzzyItem;
+use crate::sources::SourceFinder;
+use crate::launcher::{escape_field_codes, resolve_argv};
+use crate::uri::uri_to_path;
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// VS Code builds: config directory name, display name and CLI command
+const VSCODE_VARIANTS: &[(&str, &str, &str)] = &[
+    ("Code", "VS Code", "code"),
+    ("Code - Insiders", "VS Code Insiders", "code-insiders"),
+    ("Code - OSS", "Code - OSS", "code-oss"),
+    ("VSCodium", "VSCodium", "codium"),
+];
+
+// JetBrains config directory prefixes, display names and launcher scripts
+const JETBRAINS_PRODUCTS: &[(&str, &str, &[&str])] = &[
+    ("IntelliJIdea", "IntelliJ IDEA", &["idea", "intellij-idea-ultimate"]),
+    ("IdeaIC", "IntelliJ IDEA CE", &["idea", "idea-community", "intellij-idea-community"]),
+    ("PyCharmCE", "PyCharm CE", &["pycharm", "pycharm-community"]),
+    ("PyCharm", "PyCharm", &["pycharm", "pycharm-professional"]),
+    ("CLion", "CLion", &["clion"]),
+    ("GoLand", "GoLand", &["goland"]),
+    ("RustRover", "RustRover", &["rustrover"]),
+    ("WebStorm", "WebStorm", &["webstorm"]),
+    ("PhpStorm", "PhpStorm", &["phpstorm"]),
+    ("RubyMine", "RubyMine", &["rubymine"]),
+    ("Rider", "Rider", &["rider"]),
+    ("DataGrip", "DataGrip", &["datagrip"]),
+];
+
+#[derive(Default)]
+pub struct ProjectsFinder;
+
+struct Project {
+    path: String,
+    editor: &'static str,
+    exec: String,
+    argv: Vec<String>,
+    source_path: PathBuf,
+}
+
+#[derive(Deserialize, Default)]
+struct RecentlyOpened {
+    #[serde(default)]
+    entries: Vec<RecentEntry>,
+}
+
+#[derive(Deserialize)]
+struct RecentEntry {
+    #[serde(rename = "folderUri")]
+    folder_uri: Option<String>,
+    workspace: Option<RecentWorkspace>,
+}
+
+#[derive(Deserialize)]
+struct RecentWorkspace {
+    #[serde(rename = "configPath")]
+    config_path: String,
+}
+
+impl SourceFinder for ProjectsFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let icon = " ".to_string();
+        let config_dir = match dirs::config_dir() {
+            Some(dir) => dir,
+            None => return Vec::new(),
+        };
+
+        let mut projects = vscode_projects(&config_dir);
+        projects.extend(jetbrains_projects(&config_dir.join("JetBrains")));
+
+        let mut seen = HashSet::new();
+        projects.into_iter()
+            .filter(|project| seen.insert(project.exec.clone()))
+            .map(|project| {
+                let name = project.path.trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&project.path)
+                    .to_string();
+                FuzzyItem {
+                    display: format!("{} ({})", name, project.editor),
+                    exec: project.exec,
+                    argv: project.argv,
+                    target: Some(project.path.clone()),
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description: project.path,
+                    source_path: project.source_path.to_string_lossy().to_string(),
+                    search_desc: true,
+                    icon: icon.clone(),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn source_order(&self) -> usize {
+        6
+    }
+}
+
+fn vscode_projects(config_dir: &Path) -> Vec<Project> {
+    let mut projects = Vec::new();
+    for (dir, editor, command) in VSCODE_VARIANTS {
+        let command = match resolve_argv(command) {
+            Some(command) => command,
+            None => continue,
+        };
+        let global_storage = config_dir.join(dir).join("User").join("globalStorage");
+
+        // Current releases keep the list in SQLite, older ones in storage.json
+        let state_db = global_storage.join("state.vscdb");
+        let storage_json = global_storage.join("storage.json");
+        let (recent, source_path) = match read_state_db(&state_db) {
+            Some(recent) => (recent, state_db),
+            None => (read_storage_json(&storage_json).unwrap_or_default(), storage_json),
+        };
+
+        for entry in recent.entries {
+            // Remote folders only make sense as URIs, so pass them as such
+            let (uri, flag) = match (entry.folder_uri, entry.workspace) {
+                (Some(uri), _) => (uri, "--folder-uri"),
+                (None, Some(workspace)) => (workspace.config_path, "--file-uri"),
+                (None, None) => continue,
+            };
+            let path = uri_to_path(&uri)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|| uri.clone());
+            projects.push(Project {
+                path,
+                editor,
+                exec: format!("{} {} {}", command.join(" "), flag, uri),
+                argv: project_argv(&command, &[flag, &uri]),
+                source_path: source_path.clone(),
+            });
+        }
+    }
+    projects
+}
+
+fn read_state_db(path: &Path) -> Option<RecentlyOpened> {
+    if !path.exists() {
+        return None;
+    }
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+    let value: String = connection
+        .query_row(
+            "SELECT value FROM ItemTable WHERE key = 'history.recentlyOpenedPathsList'",
+            [],
+            |row| row.get(0),
+        )
+        .ok()?;
+    serde_json::from_str(&value).ok()
+}
+
+fn read_storage_json(path: &Path) -> Option<RecentlyOpened> {
+    let contents = fs::read_to_string(path).ok()?;
+    let storage: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    let list = storage.get("openedPathsList")?;
+
+    // Releases before 1.48 stored plain folder URIs in workspaces3
+    if let Some(entries) = list.get("entries") {
+        return serde_json::from_value(serde_json::json!({ "entries": entries })).ok();
+    }
+    let entries = list.get("workspaces3")?.as_array()?.iter()
+        .filter_map(|workspace| match workspace {
+            serde_json::Value::String(uri) => Some(RecentEntry { folder_uri: Some(uri.clone()), workspace: None }),
+            _ => None,
+        })
+        .collect();
+    Some(RecentlyOpened { entries })
+}
+
+fn jetbrains_projects(jetbrains_dir: &Path) -> Vec<Project> {
+    let home = dirs::home_dir().unwrap_or_default();
+    let mut config_dirs: Vec<PathBuf> = fs::read_dir(jetbrains_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+
+    // Newest versions first, so their history wins when deduplicating
+    config_dirs.sort();
+    config_dirs.reverse();
+
+    let mut projects = Vec::new();
+    for config_dir in config_dirs {
+        let dir_name = config_dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let (editor, commands) = match JETBRAINS_PRODUCTS.iter()
+            .find(|(prefix, _, _)| dir_name.strip_prefix(prefix).is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit())))
+        {
+            Some((_, editor, commands)) => (*editor, *commands),
+            None => continue,
+        };
+        let command = match commands.iter().find_map(|command| resolve_argv(command)) {
+            Some(command) => command,
+            None => continue,
+        };
+
+        for file in ["recentProjects.xml", "recentSolutions.xml"] {
+            let source_path = config_dir.join("options").join(file);
+            for path in parse_recent_projects(&source_path, &home) {
+                // Projects that have been deleted since can't be reopened
+                if !Path::new(&path).exists() {
+                    continue;
+                }
+                projects.push(Project {
+                    exec: format!("{} {}", command.join(" "), path),
+                    argv: project_argv(&command, &[&path]),
+                    path,
+                    editor,
+                    source_path: source_path.clone(),
+                });
+            }
+        }
+    }
+    projects
+}
+
+// Paths and URIs can contain spaces, so they are appended as they are
+fn project_argv(command: &[String], args: &[&str]) -> Vec<String> {
+    command.iter()
+        .map(String::as_str)
+        .chain(args.iter().copied())
+        .map(escape_field_codes)
+        .collect()
+}
+
+/// Reads project paths from a JetBrains recentProjects.xml, covering both
+/// the additionalInfo map and the older recentPaths list.
+fn parse_recent_projects(path: &Path, home: &Path) -> Vec<String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+    let document = match roxmltree::Document::parse(&contents) {
+        Ok(document) => document,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths = Vec::new();
+    for option in document.descendants().filter(|node| node.has_tag_name("option")) {
+        match option.attribute("name") {
+            Some("additionalInfo") => paths.extend(
+                option.descendants()
+                    .filter(|node| node.has_tag_name("entry"))
+                    .filter_map(|entry| entry.attribute("key")),
+            ),
+            Some("recentPaths") => paths.extend(
+                option.descendants()
+                    .filter(|node| node.has_tag_name("option"))
+                    .filter_map(|entry| entry.attribute("value")),
+            ),
+            _ => {}
+        }
+    }
+
+    let home = home.to_string_lossy();
+    let mut seen = HashSet::new();
+    paths.into_iter()
+        .map(|path| path.replace("$USER_HOME$", &home))
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+impl ProjectsFinder {
+    pub fn new() -> Self {
+        ProjectsFinder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/projects").join(name)
+    }
+
+    fn uris(recent: RecentlyOpened) -> Vec<(Option<String>, Option<String>)> {
+        recent.entries.into_iter()
+            .map(|entry| (entry.folder_uri, entry.workspace.map(|workspace| workspace.config_path)))
+            .collect()
+    }
+
+    #[test]
+    fn recent_projects_expand_the_home_directory() {
+        let paths = parse_recent_projects(&fixture("recentProjects.xml"), Path::new("/home/user"));
+        assert_eq!(paths, ["/home/user/src/fuzzyd", "/home/user/src/My Project", "/opt/shared/tool"]);
+    }
+
+    #[test]
+    fn old_recent_projects_use_recent_paths() {
+        let paths = parse_recent_projects(&fixture("recentProjects-old.xml"), Path::new("/home/user"));
+        assert_eq!(paths, ["/home/user/src/legacy", "/home/user/src/fuzzyd"]);
+    }
+
+    #[test]
+    fn storage_json_lists_folders_and_workspaces() {
+        let recent = read_storage_json(&fixture("storage.json")).unwrap();
+        assert_eq!(uris(recent), [
+            (Some("file:///home/user/src/fuzzyd".to_string()), None),
+            (None, Some("file:///home/user/work.code-workspace".to_string())),
+            (Some("vscode-remote://ssh-remote%2Bserver/srv/app".to_string()), None),
+            // Recent files are parsed too, then left out
+            (None, None),
+        ]);
+    }
+
+    #[test]
+    fn old_storage_json_lists_plain_folder_uris() {
+        let recent = read_storage_json(&fixture("storage-old.json")).unwrap();
+        assert_eq!(uris(recent), [
+            (Some("file:///home/user/src/old".to_string()), None),
+            (Some("file:///home/user/src/older".to_string()), None),
+        ]);
+    }
+}
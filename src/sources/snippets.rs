@@ -0,0 +1,180 @@
+use crate::fuzzy::{FuzzyItem, ItemAction};
+use crate::sources::SourceFinder;
+use crate::sources::time::local_zone;
+use crate::config::{expand_home, ClipboardConfig, SnippetsConfig};
+use crate::clipboard;
+use crate::error::FuzzydError;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tz::DateTime;
+use walkdir::WalkDir;
+
+pub struct SnippetsFinder {
+    file: Option<PathBuf>,
+    directory: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+struct SnippetFile {
+    #[serde(default)]
+    snippet: Vec<Snippet>,
+}
+
+#[derive(Deserialize)]
+struct Snippet {
+    name: String,
+    text: String,
+}
+
+impl SourceFinder for SnippetsFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let mut snippets = Vec::new();
+        if let Some(file) = &self.file {
+            snippets.extend(load_snippet_file(file).into_iter().map(|snippet| (snippet, file.clone())));
+        }
+        if let Some(directory) = &self.directory {
+            snippets.extend(load_snippet_directory(directory));
+        }
+
+        let icon = " ".to_string();
+        snippets.into_iter()
+            .map(|(snippet, source_path)| FuzzyItem {
+                display: snippet.name,
+                exec: snippet.text.clone(),
+                priority: 2,
+                source_order: self.source_order(),
+                description: snippet.text.split_whitespace().collect::<Vec<_>>().join(" "),
+                source_path: source_path.to_string_lossy().to_string(),
+                search_desc: true,
+                icon: icon.clone(),
+                action: ItemAction::Snippet(snippet.text),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn source_order(&self) -> usize {
+        12
+    }
+}
+
+fn load_snippet_file(path: &Path) -> Vec<Snippet> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| toml::from_str::<SnippetFile>(&contents).ok())
+        .map(|file| file.snippet)
+        .unwrap_or_default()
+}
+
+// Every file is a snippet, named after its path without the extension
+fn load_snippet_directory(directory: &Path) -> Vec<(Snippet, PathBuf)> {
+    WalkDir::new(directory)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let text = fs::read_to_string(path).ok()?;
+            let name = path.strip_prefix(directory).ok()?.with_extension("");
+            Some((
+                Snippet {
+                    name: name.to_string_lossy().to_string(),
+                    text: text.trim_end_matches('\n').to_string(),
+                },
+                path.to_path_buf(),
+            ))
+        })
+        .collect()
+}
+
+/// Fills in the `{date}`, `{time}`, `{clipboard}` and `{input:Name}`
+/// placeholders of a snippet. Inputs are asked for once per name, and
+/// `None` means the user cancelled one of the prompts.
+pub fn expand_snippet<F>(text: &str, clipboard: &ClipboardConfig, mut prompt: F) -> Result<Option<String>, FuzzydError>
+where
+    F: FnMut(&str) -> Result<Option<String>, FuzzydError>,
+{
+    lazy_static! {
+        static ref PLACEHOLDER: Regex = Regex::new(r"\{(date|time|clipboard|input:([^{}]+))\}").unwrap();
+    }
+
+    // Prompt for the inputs in the order they appear before substituting
+    let mut inputs = HashMap::new();
+    for captures in PLACEHOLDER.captures_iter(text) {
+        if let Some(name) = captures.get(2) {
+            let name = name.as_str().trim();
+            if !inputs.contains_key(name) {
+                match prompt(name)? {
+                    Some(value) => inputs.insert(name.to_string(), value),
+                    None => return Ok(None),
+                };
+            }
+        }
+    }
+
+    let now = local_zone().and_then(|zone| DateTime::now(zone.time_zone.as_ref()).ok());
+    let expanded = PLACEHOLDER.replace_all(text, |captures: &Captures| {
+        match (&captures[1], captures.get(2), &now) {
+            (_, Some(name), _) => inputs[name.as_str().trim()].clone(),
+            ("date", _, Some(now)) => format!("{}-{:02}-{:02}", now.year(), now.month(), now.month_day()),
+            ("time", _, Some(now)) => format!("{:02}:{:02}", now.hour(), now.minute()),
+            ("clipboard", _, _) => clipboard::paste(clipboard).unwrap_or_default(),
+            _ => captures[0].to_string(),
+        }
+    });
+    Ok(Some(expanded.to_string()))
+}
+
+impl SnippetsFinder {
+    pub fn new(config: SnippetsConfig) -> Self {
+        let config_dir = dirs::config_dir().map(|dir| dir.join("fuzzyd"));
+        let file = config.file.as_deref().map(expand_home)
+            .or_else(|| config_dir.as_ref().map(|dir| dir.join("snippets.toml")));
+        let directory = config.directory.as_deref().map(expand_home)
+            .or_else(|| config_dir.as_ref().map(|dir| dir.join("snippets")));
+        SnippetsFinder { file, directory }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(text: &str, inputs: &[(&str, &str)]) -> (Option<String>, Vec<String>) {
+        let clipboard = ClipboardConfig { command: None, paste_command: Some("printf pasted".to_string()) };
+        let mut asked = Vec::new();
+        let expanded = expand_snippet(text, &clipboard, |name| {
+            asked.push(name.to_string());
+            Ok(inputs.iter().find(|(input, _)| *input == name).map(|(_, value)| value.to_string()))
+        }).unwrap();
+        (expanded, asked)
+    }
+
+    #[test]
+    fn inputs_are_asked_for_once_in_order() {
+        let (expanded, asked) = expand("Dear {input:Name}, re {input: Topic}: {input:Name}", &[("Name", "Ada"), ("Topic", "engines")]);
+        assert_eq!(expanded.as_deref(), Some("Dear Ada, re engines: Ada"));
+        assert_eq!(asked, ["Name", "Topic"]);
+    }
+
+    #[test]
+    fn a_cancelled_input_cancels_the_snippet() {
+        let (expanded, asked) = expand("{input:Name} {input:Missing} {input:Other}", &[("Name", "Ada")]);
+        assert_eq!(expanded, None);
+        assert_eq!(asked, ["Name", "Missing"]);
+    }
+
+    #[test]
+    fn date_time_and_clipboard_are_filled_in() {
+        let (expanded, asked) = expand("{date} {time} [{clipboard}] {unknown}", &[]);
+        let expanded = expanded.unwrap();
+        assert!(asked.is_empty());
+        assert!(Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2} \[pasted\] \{unknown\}$").unwrap().is_match(&expanded), "{}", expanded);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::fuzzy::FuzzyItem;
+use crate::sources::SourceFinder;
+use crate::launcher::escape_field_codes;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use which::which;
+
+// Tools and runtimes that Steam installs next to the games
+const IGNORED_APPS: &[&str] = &["228980", "1070560", "1391110", "1628350"];
+
+#[derive(Default)]
+pub struct SteamFinder;
+
+impl SourceFinder for SteamFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let icon = " ".to_string();
+        let steam = match which("steam") {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => return Vec::new(),
+        };
+
+        library_dirs()
+            .par_iter()
+            .flat_map(|library| {
+                fs::read_dir(library.join("steamapps"))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| name.starts_with("appmanifest_") && name.ends_with(".acf"))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter_map(|manifest| {
+                let (app_id, name) = parse_app_manifest(&manifest)?;
+                Some(FuzzyItem {
+                    display: name,
+                    exec: format!("{} steam://rungameid/{}", steam, app_id),
+                    argv: vec![escape_field_codes(&steam), format!("steam://rungameid/{}", app_id)],
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description: "Steam game".to_string(),
+                    source_path: manifest.to_string_lossy().to_string(),
+                    search_desc: false,
+                    icon: icon.clone(),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    fn source_order(&self) -> usize {
+        3
+    }
+}
+
+/// Returns every Steam library folder, read from libraryfolders.vdf in the
+/// known Steam installation directories.
+fn library_dirs() -> Vec<PathBuf> {
+    let home = match dirs::home_dir() {
+        Some(home) => home,
+        None => return Vec::new(),
+    };
+    let roots = [
+        home.join(".steam/steam"),
+        home.join(".local/share/Steam"),
+        home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+    ];
+
+    let mut seen = HashSet::new();
+    let mut libraries = Vec::new();
+    for root in roots {
+        let mut candidates = vec![root.clone()];
+        if let Ok(contents) = fs::read_to_string(root.join("steamapps/libraryfolders.vdf")) {
+            candidates.extend(parse_library_folders(&contents));
+        }
+
+        for candidate in candidates {
+            if let Ok(canonical) = fs::canonicalize(&candidate) {
+                if seen.insert(canonical.clone()) {
+                    libraries.push(canonical);
+                }
+            }
+        }
+    }
+    libraries
+}
+
+fn parse_library_folders(contents: &str) -> Vec<PathBuf> {
+    let vdf = parse_vdf(contents);
+    let folders = match vdf.get("libraryfolders") {
+        Some(Vdf::Map(folders)) => folders,
+        _ => return Vec::new(),
+    };
+    folders.iter()
+        .filter_map(|(index, folder)| match folder {
+            // Older files map the index directly to the path, next to stats
+            // keys that aren't numbers
+            Vdf::Value(path) => index.parse::<u32>().is_ok().then(|| PathBuf::from(path)),
+            Vdf::Map(_) => folder.get("path").and_then(Vdf::as_str).map(PathBuf::from),
+        })
+        .collect()
+}
+
+fn parse_app_manifest(path: &Path) -> Option<(String, String)> {
+    let contents = fs::read_to_string(path).ok()?;
+    let manifest = parse_vdf(&contents);
+    let state = manifest.get("AppState")?;
+    let app_id = state.get("appid")?.as_str()?;
+    let name = state.get("name")?.as_str()?;
+
+    // Bit 4 of StateFlags is set once the app is fully installed
+    let installed = state.get("StateFlags")
+        .and_then(Vdf::as_str)
+        .and_then(|flags| flags.parse::<u32>().ok())
+        .is_some_and(|flags| flags & 4 != 0);
+    if !installed || IGNORED_APPS.contains(&app_id) || name.starts_with("Proton") || name.starts_with("Steam Linux Runtime") {
+        return None;
+    }
+
+    Some((app_id.to_string(), name.to_string()))
+}
+
+/// A node in Valve's KeyValues (VDF) text format.
+enum Vdf {
+    Value(String),
+    Map(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Map(entries) => entries.iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Value(_) => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Value(value) => Some(value),
+            Vdf::Map(_) => None,
+        }
+    }
+}
+
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+fn parse_vdf(input: &str) -> Vdf {
+    let mut tokens = Tokenizer { chars: input.chars().peekable() }.peekable();
+    parse_map(&mut tokens)
+}
+
+fn parse_map(tokens: &mut Peekable<Tokenizer>) -> Vdf {
+    let mut entries = Vec::new();
+    while let Some(token) = tokens.next() {
+        let key = match token {
+            Token::Str(key) => key,
+            Token::Close => break,
+            Token::Open => continue,
+        };
+        match tokens.next() {
+            Some(Token::Str(value)) => entries.push((key, Vdf::Value(value))),
+            Some(Token::Open) => entries.push((key, parse_map(tokens))),
+            Some(Token::Close) | None => break,
+        }
+    }
+    Vdf::Map(entries)
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            match self.chars.next()? {
+                c if c.is_whitespace() => continue,
+                '{' => return Some(Token::Open),
+                '}' => return Some(Token::Close),
+                '/' if self.chars.peek() == Some(&'/') => {
+                    for c in self.chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                '"' => {
+                    let mut value = String::new();
+                    while let Some(c) = self.chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => match self.chars.next() {
+                                Some('n') => value.push('\n'),
+                                Some('t') => value.push('\t'),
+                                Some(other) => value.push(other),
+                                None => break,
+                            },
+                            _ => value.push(c),
+                        }
+                    }
+                    return Some(Token::Str(value));
+                }
+                c => {
+                    // Unquoted tokens end at whitespace or a brace
+                    let mut value = c.to_string();
+                    while let Some(&c) = self.chars.peek() {
+                        if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                            break;
+                        }
+                        value.push(c);
+                        self.chars.next();
+                    }
+                    return Some(Token::Str(value));
+                }
+            }
+        }
+    }
+}
+
+impl SteamFinder {
+    pub fn new() -> Self {
+        SteamFinder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/steam").join(name)
+    }
+
+    #[test]
+    fn library_folders_skip_nested_apps() {
+        let contents = fs::read_to_string(fixture("libraryfolders.vdf")).unwrap();
+        assert_eq!(parse_library_folders(&contents), vec![
+            PathBuf::from("/home/user/.local/share/Steam"),
+            PathBuf::from("/mnt/Games Library/Steam"),
+        ]);
+    }
+
+    #[test]
+    fn old_library_folders_map_indexes_to_paths() {
+        let contents = fs::read_to_string(fixture("libraryfolders_old.vdf")).unwrap();
+        assert_eq!(parse_library_folders(&contents), vec![
+            PathBuf::from("/mnt/games/SteamLibrary"),
+            PathBuf::from("D:\\SteamLibrary"),
+        ]);
+    }
+
+    #[test]
+    fn app_manifest_unescapes_name() {
+        assert_eq!(
+            parse_app_manifest(&fixture("appmanifest_570.acf")),
+            Some(("570".to_string(), "Dota 2 \"Reborn\"\t(Test)".to_string())),
+        );
+    }
+
+    #[test]
+    fn app_manifest_skips_runtimes_and_uninstalled_apps() {
+        assert_eq!(parse_app_manifest(&fixture("appmanifest_1070560.acf")), None);
+        assert_eq!(parse_app_manifest(&fixture("appmanifest_1091500.acf")), None);
+    }
+
+    #[test]
+    fn vdf_nested_blocks() {
+        let vdf = parse_vdf(r#""a" { "b" { "c" "1" } "d" "2" } "e" "3""#);
+        let a = vdf.get("A").unwrap();
+        assert_eq!(a.get("b").and_then(|b| b.get("c")).and_then(Vdf::as_str), Some("1"));
+        assert_eq!(a.get("d").and_then(Vdf::as_str), Some("2"));
+        assert_eq!(vdf.get("e").and_then(Vdf::as_str), Some("3"));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::fuzzy::{FuzzyItem, ItemAction, QueryProvider};
+use crate::sources::SourceFinder;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tz::{DateTime, TimeZone, UtcDateTime};
+use walkdir::WalkDir;
+
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+const WEEKDAYS: &[&str] = &["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+// Common abbreviations are ambiguous or missing in the tz database, so they
+// are treated as the fixed offsets people usually mean by them
+const ABBREVIATIONS: &[(&str, i32)] = &[
+    ("utc", 0), ("gmt", 0), ("z", 0),
+    ("pst", -8 * 3600), ("pdt", -7 * 3600),
+    ("mst", -7 * 3600), ("mdt", -6 * 3600),
+    ("cst", -6 * 3600), ("cdt", -5 * 3600),
+    ("est", -5 * 3600), ("edt", -4 * 3600),
+    ("akst", -9 * 3600), ("akdt", -8 * 3600),
+    ("hst", -10 * 3600),
+    ("wet", 0), ("west", 3600),
+    ("bst", 3600),
+    ("cet", 3600), ("cest", 2 * 3600),
+    ("eet", 2 * 3600), ("eest", 3 * 3600),
+    ("msk", 3 * 3600),
+    ("ist", 5 * 3600 + 1800),
+    ("sgt", 8 * 3600), ("hkt", 8 * 3600),
+    ("kst", 9 * 3600), ("jst", 9 * 3600),
+    ("awst", 8 * 3600),
+    ("acst", 9 * 3600 + 1800), ("acdt", 10 * 3600 + 1800),
+    ("aest", 10 * 3600), ("aedt", 11 * 3600),
+    ("nzst", 12 * 3600), ("nzdt", 13 * 3600),
+];
+
+#[derive(Default)]
+pub struct TimeFinder;
+
+impl SourceFinder for TimeFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        Vec::new()
+    }
+
+    fn source_order(&self) -> usize {
+        8
+    }
+
+    fn query_providers(&self) -> Vec<Box<dyn QueryProvider>> {
+        vec![Box::new(TimeProvider::new(PathBuf::from(ZONEINFO)))]
+    }
+}
+
+/// Converts times between zones for queries like "15:00 PST in Stockholm",
+/// "now in Tokyo" or "unix 1700000000", using the system tz database.
+struct TimeProvider {
+    zoneinfo: PathBuf,
+    zone_names: OnceLock<Vec<String>>,
+}
+
+pub(crate) struct Zone {
+    pub(crate) name: String,
+    pub(crate) time_zone: TimeZone,
+}
+
+impl QueryProvider for TimeProvider {
+    fn provide(&self, query: &str) -> Vec<FuzzyItem> {
+        self.convert(query.trim()).into_iter().collect()
+    }
+}
+
+impl TimeProvider {
+    fn new(zoneinfo: PathBuf) -> Self {
+        TimeProvider { zoneinfo, zone_names: OnceLock::new() }
+    }
+
+    fn convert(&self, query: &str) -> Option<FuzzyItem> {
+        lazy_static! {
+            static ref UNIX: Regex = Regex::new(r"(?i)^(?:unix\s+|@)(-?\d+)$").unwrap();
+            static ref TIME: Regex = Regex::new(
+                r"(?i)^(?:(\d{4})-(\d{2})-(\d{2})\s+)?(\d{1,2})(?::(\d{2}))?\s*(am|pm)?(?:\s+(?:in\s+)?(.+?))?$"
+            ).unwrap();
+        }
+
+        let (lhs, target) = match split_target(query) {
+            Some((lhs, target)) => (lhs, Some(self.find_zone(target)?)),
+            None => (query, None),
+        };
+
+        if let Some(captures) = UNIX.captures(lhs) {
+            return self.render(captures[1].parse().ok()?, None, target);
+        }
+        if lhs.eq_ignore_ascii_case("now") {
+            return self.render(UtcDateTime::now().ok()?.unix_time(), None, target);
+        }
+
+        let captures = TIME.captures(lhs)?;
+        let source = match captures.get(7) {
+            Some(zone) => Some(self.find_zone(zone.as_str())?),
+            None => None,
+        };
+        // A bare number only counts as a time when a zone is involved
+        if source.is_none() && target.is_none() {
+            return None;
+        }
+
+        let number = |index: usize| captures.get(index).and_then(|m| m.as_str().parse::<u32>().ok());
+        let mut hour = number(4)?;
+        let minute = number(5).unwrap_or(0);
+        if let Some(suffix) = captures.get(6) {
+            if hour == 0 || hour > 12 {
+                return None;
+            }
+            match suffix.as_str().to_lowercase().as_str() {
+                "pm" if hour != 12 => hour += 12,
+                "am" if hour == 12 => hour = 0,
+                _ => {}
+            }
+        }
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+
+        // Without a date, the time is taken to be today in the source zone
+        let zone = match &source {
+            Some(zone) => zone.time_zone.clone(),
+            None => local_zone()?.time_zone,
+        };
+        let (year, month, day) = match (number(1), number(2), number(3)) {
+            (Some(year), Some(month), Some(day)) => (year as i32, month as u8, day as u8),
+            _ => {
+                let now = DateTime::now(zone.as_ref()).ok()?;
+                (now.year(), now.month(), now.month_day())
+            }
+        };
+        let date_time = DateTime::find(year, month, day, hour as u8, minute as u8, 0, 0, zone.as_ref())
+            .ok()?
+            .earliest()?;
+        self.render(date_time.unix_time(), source.as_ref(), target)
+    }
+
+    fn render(&self, unix_time: i64, source: Option<&Zone>, target: Option<Zone>) -> Option<FuzzyItem> {
+        let target = target.or_else(local_zone)?;
+        let utc = UtcDateTime::from_timespec(unix_time, 0).ok()?;
+        let converted = utc.project(target.time_zone.as_ref()).ok()?;
+        let result = format_date_time(&converted, &target.name);
+
+        let mut description = match source {
+            Some(source) => {
+                let original = utc.project(source.time_zone.as_ref()).ok()?;
+                format!("{} is {}", format_date_time(&original, &source.name), result)
+            }
+            None => result.clone(),
+        };
+        description.push_str(&format!(" · unix {}", unix_time));
+
+        Some(FuzzyItem {
+            display: if result.ends_with(&target.name) {
+                result.clone()
+            } else {
+                format!("{} ({})", result, target.name)
+            },
+            exec: result.clone(),
+            priority: 2,
+            source_order: 8,
+            description,
+            source_path: String::new(),
+            search_desc: false,
+            icon: " ".to_string(),
+            action: ItemAction::Copy(result),
+            ..Default::default()
+        })
+    }
+
+    /// Resolves an abbreviation, a UTC offset, a zone name or a city.
+    fn find_zone(&self, name: &str) -> Option<Zone> {
+        lazy_static! {
+            static ref OFFSET: Regex = Regex::new(r"(?i)^(?:utc|gmt)([+-])(\d{1,2})(?::?(\d{2}))?$").unwrap();
+        }
+        let name = name.trim();
+        let lower = name.to_lowercase();
+
+        if lower == "local" || lower == "here" {
+            return local_zone();
+        }
+        if let Some((_, offset)) = ABBREVIATIONS.iter().find(|(abbreviation, _)| *abbreviation == lower) {
+            return Some(Zone { name: name.to_uppercase(), time_zone: TimeZone::fixed(*offset).ok()? });
+        }
+        if let Some(captures) = OFFSET.captures(name) {
+            let hours: i32 = captures[2].parse().ok()?;
+            let minutes: i32 = captures.get(3).map_or(Ok(0), |m| m.as_str().parse()).ok()?;
+            let sign = if &captures[1] == "-" { -1 } else { 1 };
+            return Some(Zone {
+                name: name.to_uppercase(),
+                time_zone: TimeZone::fixed(sign * (hours * 3600 + minutes * 60)).ok()?,
+            });
+        }
+
+        // Zone IDs match in full, cities match the last component
+        let wanted = lower.replace(' ', "_");
+        let zone_names = self.zone_names.get_or_init(|| zone_names(&self.zoneinfo));
+        let zone_name = zone_names.iter()
+            .find(|zone| zone.to_lowercase() == wanted)
+            .or_else(|| zone_names.iter().find(|zone| {
+                zone.rsplit('/').next().is_some_and(|city| city.to_lowercase() == wanted)
+            }))?;
+        Some(Zone {
+            name: zone_name.clone(),
+            time_zone: TimeZone::from_tz_data(&fs::read(self.zoneinfo.join(zone_name)).ok()?).ok()?,
+        })
+    }
+}
+
+// Splits "<time> in <zone>" or "<time> to <zone>" at the last separator
+fn split_target(query: &str) -> Option<(&str, &str)> {
+    // The separators are ASCII, so a match always starts on a char boundary
+    let index = query.as_bytes()
+        .windows(4)
+        .rposition(|window| window.eq_ignore_ascii_case(b" in ") || window.eq_ignore_ascii_case(b" to "))?;
+    Some((query[..index].trim(), query[index + 4..].trim()))
+}
+
+pub(crate) fn local_zone() -> Option<Zone> {
+    match env::var("TZ") {
+        Ok(tz) if !tz.is_empty() => Some(Zone {
+            time_zone: TimeZone::from_posix_tz(&tz).ok()?,
+            name: tz.trim_start_matches(':').to_string(),
+        }),
+        _ => {
+            // /etc/localtime usually links into the zoneinfo directory
+            let name = fs::read_link("/etc/localtime").ok()
+                .and_then(|target| {
+                    let target = target.to_string_lossy();
+                    target.split_once("zoneinfo/").map(|(_, name)| name.to_string())
+                })
+                .unwrap_or_else(|| "local".to_string());
+            Some(Zone { name, time_zone: TimeZone::local().ok()? })
+        }
+    }
+}
+
+fn zone_names(root: &Path) -> Vec<String> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || (name != "posix" && name != "right" && !name.contains('.'))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let name = entry.path().strip_prefix(root).ok()?.to_string_lossy().to_string();
+            // Zone names are capitalized, unlike the data files next to them
+            name.starts_with(|c: char| c.is_ascii_uppercase()).then_some(name)
+        })
+        .collect()
+}
+
+fn format_date_time(date_time: &DateTime, zone_name: &str) -> String {
+    let designation = date_time.local_time_type().time_zone_designation();
+    let designation = if designation.is_empty() { zone_name } else { designation };
+    format!(
+        "{} {}-{:02}-{:02} {:02}:{:02} {}",
+        WEEKDAYS[date_time.week_day() as usize % 7],
+        date_time.year(),
+        date_time.month(),
+        date_time.month_day(),
+        date_time.hour(),
+        date_time.minute(),
+        designation,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_target_uses_the_last_separator() {
+        assert_eq!(split_target("3pm in Tokyo TO Berlin"), Some(("3pm in Tokyo", "Berlin")));
+        assert_eq!(split_target("now In UTC"), Some(("now", "UTC")));
+        assert_eq!(split_target("3pm"), None);
+    }
+
+    fn convert(query: &str) -> Option<String> {
+        let provider = TimeProvider::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/zoneinfo"));
+        provider.convert(query).map(|item| item.exec)
+    }
+
+    #[test]
+    fn times_convert_between_zones() {
+        assert_eq!(convert("2024-03-10 15:00 JST to Berlin").as_deref(), Some("Sun 2024-03-10 07:00 CET"));
+        assert_eq!(convert("2024-07-01 9am America/New_York in tokyo").as_deref(), Some("Mon 2024-07-01 22:00 JST"));
+        assert_eq!(convert("2024-07-01 12am UTC+5:30 to New York").as_deref(), Some("Sun 2024-06-30 14:30 EDT"));
+        assert_eq!(convert("unix 1700000000 in Europe/Berlin").as_deref(), Some("Tue 2023-11-14 23:13 CET"));
+    }
+
+    #[test]
+    fn the_source_zone_can_be_given_with_in() {
+        assert_eq!(convert("2024-03-10 3pm in Tokyo to Berlin").as_deref(), Some("Sun 2024-03-10 07:00 CET"));
+    }
+
+    #[test]
+    fn invalid_times_and_unknown_zones_give_nothing() {
+        assert_eq!(convert("2024-03-10 25:00 UTC to Berlin"), None);
+        assert_eq!(convert("2024-03-10 13pm UTC to Berlin"), None);
+        assert_eq!(convert("2024-03-10 10:00 UTC to Atlantis"), None);
+        assert_eq!(convert("2024-03-10 10:00 Atlantis to Berlin"), None);
+    }
+
+    #[test]
+    fn split_target_keeps_non_ascii_intact() {
+        // İ grows from 2 to 3 bytes when lowercased
+        assert_eq!(split_target("İ in 日本"), Some(("İ", "日本")));
+        assert_eq!(split_target("İİİİ 10:00 to Asia/東京"), Some(("İİİİ 10:00", "Asia/東京")));
+    }
+}
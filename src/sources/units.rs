@@ -0,0 +1,225 @@
+use crate::fuzzy::{FuzzyItem, ItemAction};
+use crate::sources::SourceFinder;
+use crate::launcher::escape_field_codes;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use which::which;
+
+const UNIT_PREFIX: &str = "app-fuzzyd-";
+
+pub struct UnitsFinder {
+    terminal: Option<Vec<String>>,
+    // Whether the launcher starts units in the user's service manager
+    user: bool,
+}
+
+struct Unit {
+    name: String,
+    description: String,
+    control_group: Option<String>,
+    usage: String,
+}
+
+impl SourceFinder for UnitsFinder {
+    fn find_entries(&self) -> Vec<FuzzyItem> {
+        let icon = " ".to_string();
+        let program = |name: &str| which(name).ok().map(|path| path.to_string_lossy().to_string());
+        let systemctl = match program("systemctl") {
+            Some(systemctl) => systemctl,
+            None => return Vec::new(),
+        };
+        let journalctl = program("journalctl").zip(self.terminal.as_ref());
+        let cgtop = program("systemd-cgtop").zip(self.terminal.as_ref());
+        let manager: &[&str] = if self.user { &["--user"] } else { &[] };
+        let command = |program: &str, args: &[&str]| -> Vec<String> {
+            [program].iter().chain(args).map(|arg| arg.to_string()).collect()
+        };
+
+        // The actions run directly, as units of their own they would be
+        // listed here and keep running after the journal is closed
+        let mut items = Vec::new();
+        for unit in list_units(|args| run(&systemctl, &[manager, args].concat())) {
+            let label = display_name(&unit.name);
+            let description = format!("{} · {}", unit.description, unit.usage);
+            let mut actions = vec![
+                ("Stop", command(&systemctl, &[manager, &["stop", &unit.name]].concat())),
+                ("Restart", command(&systemctl, &[manager, &["restart", &unit.name]].concat())),
+            ];
+            if let Some((journalctl, terminal)) = &journalctl {
+                let journal = command(journalctl, &[manager, &["-u", &unit.name, "-n", "100", "-f"]].concat());
+                actions.push(("Journal", [terminal.as_slice(), &journal].concat()));
+            }
+            if let (Some((cgtop, terminal)), Some(control_group)) = (&cgtop, &unit.control_group) {
+                actions.push(("Resources", [terminal.as_slice(), &command(cgtop, &[control_group])].concat()));
+            }
+
+            for (action, argv) in actions {
+                items.push(FuzzyItem {
+                    display: format!("{} ({})", label, action),
+                    exec: argv.join(" "),
+                    argv: argv.iter().map(|arg| escape_field_codes(arg)).collect(),
+                    action: ItemAction::Run,
+                    priority: 2,
+                    source_order: self.source_order(),
+                    description: description.clone(),
+                    source_path: unit.name.clone(),
+                    search_desc: true,
+                    icon: icon.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+        items
+    }
+
+    fn source_order(&self) -> usize {
+        11
+    }
+}
+
+/// Lists the active units started by the launcher, along with their
+/// command line, control group and current resource usage. `systemctl`
+/// runs systemctl for the launcher's service manager with the given
+/// arguments and returns its output.
+fn list_units<F>(systemctl: F) -> Vec<Unit>
+where
+    F: Fn(&[&str]) -> Option<String>,
+{
+    let pattern = format!("{}*", UNIT_PREFIX);
+    let output = match systemctl(&[
+        "list-units", "--state=active", "--plain", "--no-legend", "--no-pager", &pattern,
+    ]) {
+        Some(output) => output,
+        None => return Vec::new(),
+    };
+    let names: Vec<&str> = output.lines()
+        .filter_map(|line| line.split_whitespace().find(|field| field.starts_with(UNIT_PREFIX)))
+        .collect();
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    let mut args = vec![
+        "show", "--no-pager",
+        "-p", "Id,Description,ControlGroup,MemoryCurrent,CPUUsageNSec,TasksCurrent",
+    ];
+    args.extend(&names);
+    let properties = systemctl(&args).unwrap_or_default();
+
+    // Properties come back as one block per unit, separated by blank lines
+    properties.split("\n\n")
+        .map(|block| {
+            block.lines()
+                .filter_map(|line| line.split_once('='))
+                .collect::<HashMap<_, _>>()
+        })
+        .filter_map(|properties| {
+            let name = properties.get("Id")?.to_string();
+            Some(Unit {
+                description: properties.get("Description").unwrap_or(&"").to_string(),
+                control_group: properties.get("ControlGroup")
+                    .filter(|group| !group.is_empty())
+                    .map(|group| group.to_string()),
+                usage: format_usage(&properties),
+                name,
+            })
+        })
+        .collect()
+}
+
+fn run(command: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(command)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Unset counters are reported as "[not set]" or as u64::MAX
+fn property(properties: &HashMap<&str, &str>, name: &str) -> Option<u64> {
+    properties.get(name)?.parse().ok().filter(|value| *value != u64::MAX)
+}
+
+fn format_usage(properties: &HashMap<&str, &str>) -> String {
+    let mut usage = Vec::new();
+    if let Some(memory) = property(properties, "MemoryCurrent") {
+        usage.push(format!("{:.1} MiB", memory as f64 / (1024.0 * 1024.0)));
+    }
+    if let Some(cpu) = property(properties, "CPUUsageNSec") {
+        usage.push(format!("{:.1}s CPU", cpu as f64 / 1e9));
+    }
+    if let Some(tasks) = property(properties, "TasksCurrent") {
+        usage.push(format!("{} tasks", tasks));
+    }
+    if usage.is_empty() {
+        "No resource usage".to_string()
+    } else {
+        usage.join(" · ")
+    }
+}
+
+// app-fuzzyd-<name>-<random>.service back to the name the launcher used
+fn display_name(unit: &str) -> String {
+    let name = unit.strip_prefix(UNIT_PREFIX).unwrap_or(unit);
+    let name = name.rsplit_once('.').map_or(name, |(name, _)| name);
+    let name = match name.rsplit_once('-') {
+        Some((name, random)) if random.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => name,
+    };
+    name.replace('_', " ")
+}
+
+impl UnitsFinder {
+    pub fn new(terminal: Option<Vec<String>>, user: bool) -> Self {
+        UnitsFinder { terminal, user }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/units").join(name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn list_units_reads_properties_of_listed_units() {
+        let units = list_units(|args| match args[0] {
+            "list-units" => Some(fixture("list-units")),
+            "show" => {
+                assert_eq!(&args[4..], ["app-fuzzyd-firefox-123456.service", "app-fuzzyd-my_editor-654321.scope"]);
+                Some(fixture("show"))
+            }
+            _ => None,
+        });
+        let units: Vec<_> = units.iter()
+            .map(|unit| (unit.name.as_str(), unit.description.as_str(), unit.control_group.as_deref(), unit.usage.as_str()))
+            .collect();
+        assert_eq!(units, vec![
+            (
+                "app-fuzzyd-firefox-123456.service",
+                "/usr/bin/firefox",
+                Some("/user.slice/user-1000.slice/user@1000.service/app.slice/app-fuzzyd-firefox-123456.service"),
+                "512.0 MiB · 12.5s CPU · 80 tasks",
+            ),
+            ("app-fuzzyd-my_editor-654321.scope", "/usr/bin/editor", None, "No resource usage"),
+        ]);
+    }
+
+    #[test]
+    fn list_units_without_systemctl() {
+        assert!(list_units(|_| None).is_empty());
+    }
+
+    #[test]
+    fn display_name_strips_prefix_and_random_suffix() {
+        assert_eq!(display_name("app-fuzzyd-my_editor-654321.scope"), "my editor");
+        assert_eq!(display_name("app-fuzzyd-firefox.service"), "firefox");
+    }
+}
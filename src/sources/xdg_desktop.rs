@@ -1,36 +1,65 @@
 use walkdir::WalkDir;
 use xdg::BaseDirectories;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use regex::Regex;
-use crate::fuzzy::FuzzyItem;
+use crate::fuzzy::{FuzzyItem, ItemAction};
+use crate::desktop_file::{split_list, unescape, DesktopFile, Diagnostic, Severity};
 use lazy_static::lazy_static;
 use crate::sources::SourceFinder;
+use crate::config::DesktopConfig;
+use crate::launcher::{escape_field_codes, expand_field_codes, terminal_argv};
+use crate::mime::mime_type;
+use crate::mimeapps::default_application;
 use rayon::prelude::*;
-use std::path::PathBuf;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use which::which;
 
-pub struct XdgDesktopFinder;
+lazy_static! {
+    static ref LOCALES: Vec<String> = message_locales(
+        &["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+    );
+    static ref CURRENT_DESKTOPS: Vec<String> = env::var("XDG_CURRENT_DESKTOP")
+        .map(|desktops| desktops.split(':').filter(|desktop| !desktop.is_empty()).map(String::from).collect())
+        .unwrap_or_default();
+}
+
+const ICON: &str = "  ";
+
+#[derive(Default)]
+pub struct XdgDesktopFinder {
+    config: DesktopConfig,
+    terminal: Option<Vec<String>>,
+    nested_actions: bool,
+    // Whether to check the files for mistakes while loading them
+    check: bool,
+    diagnostics: Mutex<Vec<Diagnostic>>,
+}
 
 impl SourceFinder for XdgDesktopFinder {
     fn find_entries(&self) -> Vec<FuzzyItem> {
-        let icon = "  ".to_string();
-        let xdg_dirs = BaseDirectories::new().expect("Failed to get XDG directories");
-        let mut data_dirs: Vec<PathBuf> = xdg_dirs.get_data_dirs();
-
-        // Add the user-specific directory
-        data_dirs.push(xdg_dirs.get_data_home());
-
-        data_dirs.par_iter()
-            .flat_map(|dir| {
-                let applications_dir = dir.join("applications");
-                WalkDir::new(applications_dir)
-                    .into_iter()
-                    .par_bridge()
-                    .filter_map(|e| e.ok())
-                    .filter(|e| e.path().extension().map_or(false, |ext| ext == "desktop"))
-                    .flat_map(|entry| parse_desktop_file(entry.path(), &icon))
-                    .collect::<Vec<_>>()
+        let icon = ICON.to_string();
+        desktop_files().par_iter()
+            .flat_map(|(id, path)| {
+                let (file, mut diagnostics) = match DesktopFile::read(path) {
+                    Ok(parsed) => parsed,
+                    Err(err) => {
+                        self.report(vec![read_error(path, &err)]);
+                        return Vec::new();
+                    }
+                };
+                if self.check {
+                    diagnostics = check_desktop_entry(&file, diagnostics);
+                }
+                self.report(diagnostics);
+                match entry_groups(file) {
+                    Some(groups) => desktop_items(path, groups, id, &icon, self.config.show_hidden, self.terminal.as_deref(), self.nested_actions),
+                    None => Vec::new(),
+                }
             })
             .collect()
     }
@@ -38,84 +67,626 @@ impl SourceFinder for XdgDesktopFinder {
     fn source_order(&self) -> usize {
         0 // Desktop entries have higher priority
     }
+
+    fn warnings(&self) -> Vec<String> {
+        let mut diagnostics = std::mem::take(&mut *self.diagnostics.lock().unwrap());
+        diagnostics.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        diagnostics.iter().map(Diagnostic::to_string).collect()
+    }
+}
+
+/// The desktop files of all data dirs with their desktop file IDs. The
+/// first data dir to provide an ID wins, even if that copy is hidden, which
+/// is how users remove system entries.
+pub(crate) fn desktop_files() -> &'static [(String, PathBuf)] {
+    static DESKTOP_FILES: OnceLock<Vec<(String, PathBuf)>> = OnceLock::new();
+    DESKTOP_FILES.get_or_init(|| {
+        let xdg_dirs = BaseDirectories::new().expect("Failed to get XDG directories");
+
+        // The user-specific directory takes precedence over the system ones
+        let mut data_dirs: Vec<PathBuf> = vec![xdg_dirs.get_data_home()];
+        data_dirs.extend(xdg_dirs.get_data_dirs());
+        find_desktop_files(&data_dirs)
+    })
 }
 
-fn parse_desktop_file(path: &std::path::Path, icon: &str) -> Vec<FuzzyItem> { // Add icon parameter
-    let file = match File::open(path) {
-        Ok(file) => file,
-        Err(_) => return Vec::new(), // Return an empty vector if file can't be opened
+fn find_desktop_files(data_dirs: &[PathBuf]) -> Vec<(String, PathBuf)> {
+    let mut seen = HashSet::new();
+    data_dirs.iter()
+        .flat_map(|dir| {
+            let applications_dir = dir.join("applications");
+            WalkDir::new(&applications_dir)
+                .follow_links(true)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "desktop"))
+                .filter_map(|entry| {
+                    let id = desktop_file_id(&applications_dir, entry.path())?;
+                    Some((id, entry.into_path()))
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|(id, _)| seen.insert(id.clone()))
+        .collect()
+}
+
+/// The argv that opens a file or URL with the default application for its
+/// MIME type, escaped for `expand_field_codes`. Only applications can be
+/// the default, links can't.
+pub(crate) fn default_opener(target: &str, terminal: Option<&[String]>) -> Option<Vec<String>> {
+    let entry = |id: &str| {
+        let (_, path) = desktop_files().iter().find(|(file_id, _)| file_id == id)?;
+        handler_items(id, path, terminal, false).into_iter()
+            .find(|item| item.id.as_deref() == Some(id) && item.action == ItemAction::Launch)
     };
-    let reader = BufReader::new(file);
+    let id = default_application(&mime_type(target), |id| entry(id).is_some())?;
+    let argv = expand_field_codes(&entry(&id)?.argv, &[target.to_string()]);
+    Some(argv.iter().map(|arg| escape_field_codes(arg)).collect())
+}
 
-    let mut items = Vec::new();
-    let mut current_section = String::new();
-    let mut main_item = HashMap::new();
+/// The items of every application that can be picked to open files, see
+/// `handler_items`.
+pub(crate) fn handlers(terminal: Option<&[String]>, nested_actions: bool) -> Vec<FuzzyItem> {
+    desktop_files().par_iter()
+        .flat_map(|(id, path)| handler_items(id, path, terminal, nested_actions))
+        .collect()
+}
+
+/// The items of an application used as a MIME handler. Handlers don't have
+/// to be shown in menus, so only a deleted entry or a missing TryExec
+/// binary leaves one out.
+fn handler_items(id: &str, path: &Path, terminal: Option<&[String]>, nested_actions: bool) -> Vec<FuzzyItem> {
+    let (mut main_item, action_items) = match read_desktop_file(path) {
+        Some(groups) => groups,
+        None => return Vec::new(),
+    };
+    if !is_installed(&main_item) {
+        return Vec::new();
+    }
+    for key in ["NoDisplay", "OnlyShowIn", "NotShowIn"] {
+        main_item.remove(key);
+    }
+    desktop_items(path, (main_item, action_items), id, ICON, false, terminal, nested_actions)
+}
+
+/// The first installed terminal from the xdg-terminals.list files of the
+/// xdg-terminal-exec spec, as a command that runs the arguments after it.
+pub(crate) fn preferred_terminal() -> Option<Vec<String>> {
+    let xdg_dirs = BaseDirectories::new().ok()?;
+    let mut dirs = vec![xdg_dirs.get_config_home()];
+    dirs.extend(xdg_dirs.get_config_dirs());
+    dirs.push(xdg_dirs.get_data_home().join("xdg-terminal-exec"));
+    dirs.extend(xdg_dirs.get_data_dirs().into_iter().map(|dir| dir.join("xdg-terminal-exec")));
+
+    let mut file_names: Vec<String> = CURRENT_DESKTOPS.iter()
+        .map(|desktop| format!("{}-xdg-terminals.list", desktop.to_lowercase()))
+        .collect();
+    file_names.push("xdg-terminals.list".to_string());
+
+    // Lines are desktop file IDs, optionally with an action, and a leading
+    // "-" excludes a terminal
+    dirs.iter()
+        .flat_map(|dir| file_names.iter().map(move |name| dir.join(name)))
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|contents| contents.lines().map(str::trim).map(String::from).collect::<Vec<_>>())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('-'))
+        .find_map(|line| {
+            let id = line.trim_start_matches('+').split(':').next()?.to_string();
+            let (_, path) = desktop_files().iter().find(|(file_id, _)| *file_id == id)?;
+            let (entries, _) = read_desktop_file(path)?;
+            // Terminals hidden from menus can still be used
+            if !is_installed(&entries) {
+                return None;
+            }
+            let name = localized(&entries, "Name").cloned().unwrap_or_default();
+            let icon = entries.get("Icon").cloned().unwrap_or_default();
+            let argv = parse_exec(entries.get("Exec")?, &icon, &name, path)?;
+            let mut argv = expand_field_codes(&argv, &[]);
+            let executable = argv.first_mut()?;
+            *executable = which(&*executable).ok()?.to_str()?.to_string();
+
+            // The option that makes the terminal run a command, -e by default
+            let exec_arg = entries.get("X-ExecArg").or_else(|| entries.get("ExecArg"));
+            match exec_arg.map(String::as_str) {
+                Some("") => {}
+                Some(exec_arg) => argv.push(exec_arg.to_string()),
+                None => argv.push("-e".to_string()),
+            }
+            Some(argv)
+        })
+}
+
+/// A `Type=Directory` entry, which names and describes a menu.
+pub(crate) struct DirectoryEntry {
+    pub name: String,
+    pub comment: Option<String>,
+    pub icon: Option<String>,
+}
+
+/// Reads a .directory file from the desktop-directories of the data dirs,
+/// as referred to by menu files.
+pub(crate) fn directory_entry(file_name: &str) -> Option<DirectoryEntry> {
+    let xdg_dirs = BaseDirectories::new().ok()?;
+    let mut data_dirs: Vec<PathBuf> = vec![xdg_dirs.get_data_home()];
+    data_dirs.extend(xdg_dirs.get_data_dirs());
+    let (entries, _) = data_dirs.iter()
+        .map(|dir| dir.join("desktop-directories").join(file_name))
+        .find_map(|path| read_desktop_file(&path))?;
+    if entries.get("Type").is_some_and(|entry_type| entry_type != "Directory") {
+        return None;
+    }
+    Some(DirectoryEntry {
+        name: localized(&entries, "Name")?.clone(),
+        comment: localized(&entries, "Comment").cloned(),
+        icon: entries.get("Icon").filter(|icon| !icon.is_empty()).cloned(),
+    })
+}
+
+/// The argv that opens a file or URL with its default application, or with
+/// xdg-open when there is none. Links are only resolved when they are
+/// opened, since looking up every link's application slows down loading.
+pub(crate) fn open_argv(target: &str, terminal: Option<&[String]>) -> Option<Vec<String>> {
+    default_opener(target, terminal).or_else(|| {
+        let xdg_open = which("xdg-open").ok()?;
+        Some(vec![escape_field_codes(xdg_open.to_str()?), escape_field_codes(target)])
+    })
+}
+
+/// The desktop file ID is the path below the applications directory, with
+/// "/" replaced by "-".
+fn desktop_file_id(applications_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(applications_dir).ok()?;
+    Some(relative.to_str()?.replace('/', "-"))
+}
+
+type DesktopGroup = HashMap<String, String>;
+
+/// Reads the `[Desktop Entry]` group and the `[Desktop Action ...]` groups
+/// of a desktop file, keyed by their section names.
+fn read_desktop_file(path: &Path) -> Option<(DesktopGroup, HashMap<String, DesktopGroup>)> {
+    let (file, _) = DesktopFile::read(path).ok()?;
+    entry_groups(file)
+}
+
+fn entry_groups(file: DesktopFile) -> Option<(DesktopGroup, HashMap<String, DesktopGroup>)> {
+    let mut main_item = None;
     let mut action_items = HashMap::new();
+    for group in file.groups {
+        if group.name == "Desktop Entry" {
+            main_item = Some(group.entries);
+        } else if group.name.starts_with("Desktop Action ") {
+            action_items.insert(group.name, group.entries);
+        }
+    }
+    Some((main_item?, action_items))
+}
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(line) => line,
-            Err(_) => continue, // Skip lines that can't be read
-        };
-        if line.starts_with('[') && line.ends_with(']') {
-            current_section = line[1..line.len() - 1].to_string();
-        } else if !line.trim().is_empty() {
-            let parts: Vec<&str> = line.splitn(2, '=').collect();
-            if parts.len() == 2 {
-                let key = parts[0].trim();
-                let value = parts[1].trim();
-                if current_section == "Desktop Entry" {
-                    main_item.insert(key.to_string(), value.to_string());
-                } else if current_section.starts_with("Desktop Action") {
-                    action_items
-                        .entry(current_section.clone())
-                        .or_insert_with(HashMap::new)
-                        .insert(key.to_string(), value.to_string());
-                }
+/// Checks desktop files for mistakes and tells why their entries are
+/// skipped or hidden. Without paths, every desktop file fuzzyd lists is
+/// checked.
+pub fn check_desktop_files(paths: &[PathBuf]) -> Vec<Diagnostic> {
+    if paths.is_empty() {
+        return desktop_files().iter().flat_map(|(_, path)| check_desktop_file(path)).collect();
+    }
+    paths.iter().flat_map(|path| check_desktop_file(path)).collect()
+}
+
+// Keys that have to be true or false
+const BOOLEAN_KEYS: &[&str] = &["NoDisplay", "Hidden", "DBusActivatable", "Terminal", "StartupNotify", "PrefersNonDefaultGPU", "SingleMainWindow"];
+
+fn check_desktop_file(path: &Path) -> Vec<Diagnostic> {
+    match DesktopFile::read(path) {
+        Ok((file, diagnostics)) => check_desktop_entry(&file, diagnostics),
+        Err(err) => vec![read_error(path, &err)],
+    }
+}
+
+fn read_error(path: &Path, err: &std::io::Error) -> Diagnostic {
+    Diagnostic { path: path.to_path_buf(), line: 0, severity: Severity::Error, problem: format!("can't be read: {}", err) }
+}
+
+// Adds the problems with the entry itself to the ones found while parsing
+fn check_desktop_entry(file: &DesktopFile, mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    let path = file.path.as_path();
+    let diagnostic = |line: usize, severity: Severity, problem: String| {
+        Diagnostic { path: path.to_path_buf(), line, severity, problem }
+    };
+    let entry = match file.group("Desktop Entry") {
+        Some(entry) => entry,
+        None => return diagnostics,
+    };
+    let entries = &entry.entries;
+
+    for key in BOOLEAN_KEYS {
+        if let Some(value) = entries.get(*key).filter(|value| *value != "true" && *value != "false") {
+            diagnostics.push(diagnostic(entry.line_of(key), Severity::Warning, format!("{}={} is neither true nor false", key, value)));
+        }
+    }
+
+    let entry_type = entries.get("Type").map(String::as_str);
+    match entry_type {
+        None => diagnostics.push(diagnostic(entry.line, Severity::Warning, "Type is missing, so the entry is treated as an application".to_string())),
+        Some("Application" | "Link" | "Directory") => {}
+        Some(other) => diagnostics.push(diagnostic(entry.line_of("Type"), Severity::Error, format!("Type={} is not Application, Link or Directory, so the entry is skipped", other))),
+    }
+    if localized(entries, "Name").is_none() {
+        diagnostics.push(diagnostic(entry.line, Severity::Error, "Name is missing, so the entry is skipped".to_string()));
+    }
+    match entry_type {
+        Some("Application") | None => diagnostics.extend(check_exec(entries, path).map(|problem| {
+            diagnostic(entry.line_of("Exec"), Severity::Error, format!("{}, so the entry is skipped", problem))
+        })),
+        Some("Link") if !entries.contains_key("URL") => {
+            diagnostics.push(diagnostic(entry.line, Severity::Error, "URL is missing, so the link is skipped".to_string()));
+        }
+        _ => {}
+    }
+    if let Some(reason) = hidden_reason(entries, &CURRENT_DESKTOPS) {
+        diagnostics.push(diagnostic(entry.line_of(reason.key()), Severity::Info, format!("the entry is hidden by {}, --all lists it anyway", reason)));
+    }
+
+    // Actions have to be listed and have a group of their own
+    let action_ids = entries.get("Actions").map(|actions| split_list(actions)).unwrap_or_default();
+    for action_id in &action_ids {
+        let section = format!("Desktop Action {}", action_id);
+        let action = match file.group(&section) {
+            Some(action) => action,
+            None => {
+                diagnostics.push(diagnostic(entry.line_of("Actions"), Severity::Warning, format!("action {} has no [{}] group", action_id, section)));
+                continue;
             }
+        };
+        if localized(&action.entries, "Name").is_none() {
+            diagnostics.push(diagnostic(action.line, Severity::Warning, format!("[{}] has no Name, so the action is skipped", section)));
+        }
+        if let Some(problem) = check_exec(&action.entries, path) {
+            diagnostics.push(diagnostic(action.line_of("Exec"), Severity::Warning, format!("{}, so the action is skipped", problem)));
         }
     }
+    for group in &file.groups {
+        let listed = group.name.strip_prefix("Desktop Action ").is_none_or(|action_id| action_ids.iter().any(|id| id == action_id));
+        if !listed {
+            diagnostics.push(diagnostic(group.line, Severity::Warning, format!("[{}] is not listed in Actions, so it is skipped", group.name)));
+        }
+    }
+
+    if path.extension().is_some_and(|ext| ext == "desktop") {
+        diagnostics.extend(check_location(path).map(|problem| diagnostic(0, Severity::Info, problem)));
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+fn check_exec(entries: &DesktopGroup, path: &Path) -> Option<String> {
+    match entries.get("Exec") {
+        None => Some("Exec is missing".to_string()),
+        Some(exec) if parse_exec(exec, "", "", path).is_none() => Some(format!("Exec={} can't be parsed", exec)),
+        Some(_) => None,
+    }
+}
+
+// Only files in the applications dirs are listed, and the first one with a
+// desktop file ID hides the others
+fn check_location(path: &Path) -> Option<String> {
+    let path = std::path::absolute(path).ok()?;
+    let xdg_dirs = BaseDirectories::new().ok()?;
+    let mut data_dirs: Vec<PathBuf> = vec![xdg_dirs.get_data_home()];
+    data_dirs.extend(xdg_dirs.get_data_dirs());
+    let id = match data_dirs.iter().find_map(|dir| desktop_file_id(&dir.join("applications"), &path)) {
+        Some(id) => id,
+        None => return Some("the file is not in the applications directory of XDG_DATA_HOME or XDG_DATA_DIRS, so it isn't listed".to_string()),
+    };
+    let (_, listed) = desktop_files().iter().find(|(file_id, _)| *file_id == id)?;
+    (*listed != path).then(|| format!("{} has the same desktop file ID {} and takes precedence", listed.display(), id))
+}
+
+fn desktop_items(
+    path: &Path,
+    (main_item, action_items): (DesktopGroup, HashMap<String, DesktopGroup>),
+    id: &str,
+    icon: &str,
+    show_hidden: bool,
+    terminal: Option<&[String]>,
+    nested_actions: bool,
+) -> Vec<FuzzyItem> {
+    let mut items = Vec::new();
+
+    // Only applications and links can be launched. Entries without a Type
+    // are treated as applications, as many launchers do
+    let link = match main_item.get("Type").map(String::as_str) {
+        Some("Application") | None => None,
+        Some("Link") => match main_item.get("URL") {
+            Some(url) => Some(url.clone()),
+            None => return items,
+        },
+        Some(_) => return items,
+    };
+
+    // Entries that shouldn't be shown are only listed on request, with the reason
+    let hidden_reason = hidden_reason(&main_item, &CURRENT_DESKTOPS);
+    if hidden_reason.is_some() && !show_hidden {
+        return items;
+    }
+
+    let app_name = localized(&main_item, "Name").cloned().unwrap_or_default();
+    let app_icon = main_item.get("Icon").cloned().unwrap_or_default();
+    // Terminal applications are wrapped in a terminal named after the entry
+    let in_terminal = main_item.get("Terminal").is_some_and(|value| value == "true");
+    let app_id = id.strip_suffix(".desktop").unwrap_or(id);
+    let argv = |entries: &HashMap<String, String>| {
+        let argv = entries.get("Exec").and_then(|exec| parse_exec(exec, &app_icon, &app_name, path))?;
+        Some(match terminal {
+            Some(terminal) if in_terminal => terminal_argv(terminal, &app_name, app_id, &argv),
+            _ => argv,
+        })
+    };
 
     // Create main FuzzyItem
-    if let Some(name) = main_item.get("Name") {
-        if let Some(exec) = main_item.get("Exec") {
-            if let Some(item) = create_fuzzy_item(
+    if let Some(name) = localized(&main_item, "Name") {
+        // Links get their argv when opened, see open_argv
+        let main_argv = match &link {
+            Some(_) => Some(Vec::new()),
+            None => argv(&main_item),
+        };
+        if let Some(argv) = main_argv {
+            let item = create_fuzzy_item(
                 name,
-                exec,
-                main_item.get("Comment").unwrap_or(&String::new()),
-                main_item.get("Icon").unwrap_or(&String::new()),
+                argv,
+                // The generic name describes entries without a comment,
+                // and the URL describes links
+                localized(&main_item, "Comment")
+                    .or_else(|| localized(&main_item, "GenericName"))
+                    .or(link.as_ref())
+                    .unwrap_or(&String::new()),
                 path,
                 "Desktop Entry",
-                icon, // Pass icon
-            ) {
-                items.push(item);
-            }
+                icon,
+                search_keywords(&main_item, &LOCALES),
+            );
+            let (exec, action) = match &link {
+                Some(url) => (url.clone(), ItemAction::OpenDefault(url.clone())),
+                None => (item.exec.clone(), ItemAction::Launch),
+            };
+            items.push(FuzzyItem {
+                id: Some(id.to_string()),
+                exec,
+                action,
+                mime_types: main_item.get("MimeType").map(|types| split_list(types)).unwrap_or_default(),
+                generic_name: localized(&main_item, "GenericName").cloned(),
+                categories: main_item.get("Categories").map(|categories| split_list(categories)).unwrap_or_default(),
+                target: link.clone(),
+                ..item
+            });
         }
     }
 
-    // Create FuzzyItems for Desktop Actions
-    for (section, action) in action_items {
-        if let Some(name) = action.get("Name") {
-            if let Some(exec) = action.get("Exec") {
-                if let Some(item) = create_fuzzy_item(
+    // Create FuzzyItems for the actions listed in Actions=, in that order
+    let action_ids = main_item.get("Actions").map(|actions| split_list(actions)).unwrap_or_default();
+    let mut action_names = Vec::new();
+    for action_id in &action_ids {
+        let section = format!("Desktop Action {}", action_id);
+        let action = match action_items.get(&section) {
+            Some(action) => action,
+            None => continue,
+        };
+        if let Some(name) = localized(action, "Name") {
+            if let Some(argv) = argv(action) {
+                let item = create_fuzzy_item(
                     name,
-                    exec,
-                    action.get("Comment").unwrap_or(&String::new()),
-                    main_item.get("Icon").unwrap_or(&String::new()),
+                    argv,
+                    localized(action, "Comment").unwrap_or(&String::new()),
                     path,
                     &section,
-                    icon, // Pass icon
-                ) {
-                    items.push(item);
-                }
+                    icon,
+                    search_keywords(action, &LOCALES),
+                );
+                items.push(FuzzyItem {
+                    id: Some(format!("{}:{}", id, action_id)),
+                    icon_name: action.get("Icon").filter(|icon| !icon.is_empty()).cloned(),
+                    ..item
+                });
+                action_names.push(name.clone());
             }
         }
     }
 
+    if let Some(reason) = hidden_reason {
+        for item in &mut items {
+            item.description = format!("Hidden by {}: {}", reason, item.description);
+        }
+    }
+
+    // Actions run in the same directory and on the same GPU as the entry
+    let working_dir = main_item.get("Path").filter(|dir| !dir.is_empty());
+    let prefers_non_default_gpu = main_item.get("PrefersNonDefaultGPU").is_some_and(|value| value == "true");
+    let categories = main_item.get("Categories").map(|categories| split_list(categories)).unwrap_or_default();
+    let glyph = category_glyph(&categories).unwrap_or(icon);
+    for item in &mut items {
+        item.icon = glyph.to_string();
+        item.working_dir = working_dir.cloned();
+        item.prefers_non_default_gpu = prefers_non_default_gpu;
+        if item.icon_name.is_none() {
+            item.icon_name = main_item.get("Icon").filter(|icon| !icon.is_empty()).cloned();
+        }
+    }
+
+    // Nested actions are listed under the entry instead of next to it
+    if nested_actions && items.first().is_some_and(|item| item.id.as_deref() == Some(id)) {
+        let mut main = items.remove(0);
+        main.actions = items.into_iter()
+            .zip(action_names)
+            .map(|(action, name)| FuzzyItem { display: format!("{} → {}", main.display, name), ..action })
+            .collect();
+        return vec![main];
+    }
+
     items
 }
 
-fn create_fuzzy_item(name: &str, exec: &str, description: &str, icon: &str, path: &std::path::Path, section: &str, source_icon: &str) -> Option<FuzzyItem> { // Add source_icon parameter
+// Nerd Font glyphs for entries by category, the more specific ones first
+const CATEGORY_GLYPHS: &[(&str, &str)] = &[
+    ("TerminalEmulator", " "),
+    ("WebBrowser", " "),
+    ("FileManager", " "),
+    ("TextEditor", " "),
+    ("Email", " "),
+    ("InstantMessaging", " "),
+    ("Chat", " "),
+    ("Network", " "),
+    ("Graphics", " "),
+    ("Audio", " "),
+    ("Video", " "),
+    ("AudioVideo", " "),
+    ("Development", " "),
+    ("Game", " "),
+    ("Office", " "),
+    ("Education", " "),
+    ("Science", " "),
+    ("Settings", " "),
+    ("System", " "),
+    ("Utility", " "),
+];
+
+/// The glyph shown for an entry when its icon can't be drawn.
+fn category_glyph(categories: &[String]) -> Option<&'static str> {
+    CATEGORY_GLYPHS.iter()
+        .find(|(category, _)| categories.iter().any(|c| c == category))
+        .map(|(_, glyph)| *glyph)
+}
+
+/// The key that hides an entry, shown as `Key=value`.
+#[derive(Debug, PartialEq)]
+enum HiddenReason {
+    Hidden,
+    NoDisplay,
+    OnlyShowIn(String),
+    NotShowIn(String),
+    TryExec(String),
+}
+
+impl HiddenReason {
+    fn key(&self) -> &'static str {
+        match self {
+            HiddenReason::Hidden => "Hidden",
+            HiddenReason::NoDisplay => "NoDisplay",
+            HiddenReason::OnlyShowIn(_) => "OnlyShowIn",
+            HiddenReason::NotShowIn(_) => "NotShowIn",
+            HiddenReason::TryExec(_) => "TryExec",
+        }
+    }
+}
+
+impl fmt::Display for HiddenReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HiddenReason::Hidden | HiddenReason::NoDisplay => write!(f, "{}=true", self.key()),
+            HiddenReason::OnlyShowIn(value) | HiddenReason::NotShowIn(value) | HiddenReason::TryExec(value) => {
+                write!(f, "{}={}", self.key(), value)
+            }
+        }
+    }
+}
+
+/// Applies the spec's visibility rules for the given current desktops,
+/// returning why an entry is hidden.
+fn hidden_reason(entries: &HashMap<String, String>, desktops: &[String]) -> Option<HiddenReason> {
+    if entries.get("Hidden").is_some_and(|value| value == "true") {
+        return Some(HiddenReason::Hidden);
+    }
+    if entries.get("NoDisplay").is_some_and(|value| value == "true") {
+        return Some(HiddenReason::NoDisplay);
+    }
+    if let Some(only_show_in) = entries.get("OnlyShowIn") {
+        if !split_list(only_show_in).iter().any(|desktop| desktops.contains(desktop)) {
+            return Some(HiddenReason::OnlyShowIn(only_show_in.clone()));
+        }
+    }
+    if let Some(not_show_in) = entries.get("NotShowIn") {
+        if split_list(not_show_in).iter().any(|desktop| desktops.contains(desktop)) {
+            return Some(HiddenReason::NotShowIn(not_show_in.clone()));
+        }
+    }
+    match entries.get("TryExec") {
+        Some(try_exec) if which(try_exec).is_err() => Some(HiddenReason::TryExec(try_exec.clone())),
+        _ => None,
+    }
+}
+
+/// Whether an entry can be used even when it's hidden from menus, as a
+/// terminal or a MIME handler: it isn't deleted with Hidden=true and its
+/// TryExec binary exists.
+fn is_installed(entries: &HashMap<String, String>) -> bool {
+    entries.get("Hidden").is_none_or(|value| value != "true")
+        && entries.get("TryExec").is_none_or(|try_exec| which(try_exec).is_ok())
+}
+
+/// Looks up a localized key, trying `Key[lang_COUNTRY@MODIFIER]`,
+/// `Key[lang_COUNTRY]`, `Key[lang@MODIFIER]` and `Key[lang]` before `Key`.
+fn localized<'a>(entries: &'a HashMap<String, String>, key: &str) -> Option<&'a String> {
+    localized_in(entries, key, &LOCALES)
+}
+
+fn localized_in<'a>(entries: &'a HashMap<String, String>, key: &str, locales: &[String]) -> Option<&'a String> {
+    locales.iter()
+        .find_map(|locale| entries.get(&format!("{}[{}]", key, locale)))
+        .or_else(|| entries.get(key))
+}
+
+// Keywords are searchable, and so are the untranslated keywords and name
+// when translations are shown instead
+fn search_keywords(entries: &HashMap<String, String>, locales: &[String]) -> Vec<String> {
+    let mut keywords: Vec<String> = localized_in(entries, "Keywords", locales)
+        .map(|keywords| split_list(keywords))
+        .unwrap_or_default();
+    if let Some(untranslated) = entries.get("Keywords") {
+        for keyword in split_list(untranslated) {
+            if !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+    }
+    if let Some(name) = entries.get("Name") {
+        if localized_in(entries, "Name", locales) != Some(name) {
+            keywords.push(name.clone());
+        }
+    }
+    keywords
+}
+
+/// The locale variants to match localized keys against, most specific
+/// first, from the value of the LC_MESSAGES category.
+fn message_locales(locale: &str) -> Vec<String> {
+    // lang_COUNTRY.ENCODING@MODIFIER, where the encoding is never matched
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut locales = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        locales.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        locales.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        locales.push(format!("{}@{}", lang, modifier));
+    }
+    locales.push(lang.to_string());
+    locales
+}
+
+fn create_fuzzy_item(name: &str, argv: Vec<String>, description: &str, path: &Path, section: &str, source_icon: &str, keywords: Vec<String>) -> FuzzyItem {
     let source_path = if section.starts_with("Desktop Action") {
         format!("{}:{}", path.to_str().unwrap_or(""), section)
     } else {
@@ -128,42 +699,319 @@ fn create_fuzzy_item(name: &str, exec: &str, description: &str, icon: &str, path
         (description.to_string(), true)
     };
 
-    let parsed_exec = parse_exec(exec, icon);
-
-    Some(FuzzyItem {
+    FuzzyItem {
         display: if section.starts_with("Desktop Action") {
             format!("{} ({})", name, section.strip_prefix("Desktop Action ").unwrap_or(section))
         } else {
             name.to_string()
         },
-        exec: parsed_exec,
+        exec: expand_field_codes(&argv, &[]).join(" "),
+        argv,
         priority: 2,
         source_order: 0,
         description,
         source_path,
         search_desc,
-        icon: source_icon.to_string(), // Add this line
-    })
+        icon: source_icon.to_string(),
+        keywords,
+        ..Default::default()
+    }
 }
 
-pub fn parse_exec(exec: &str, icon: &str) -> String {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"%[fFuUdDnNvmck]").unwrap();
+/// Splits an Exec value into arguments following the Desktop Entry spec:
+/// string escapes, double quoted arguments and field codes. `%c`, `%k` and
+/// `%i` are filled in, while `%%` and the file and URL codes are left for
+/// the launcher. Returns `None` when the value is malformed.
+fn parse_exec(exec: &str, icon: &str, name: &str, path: &Path) -> Option<Vec<String>> {
+    let exec = unescape(exec);
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                args.extend(current.take());
+            }
+            '"' => {
+                // Inside quotes, a backslash escapes ", `, $ and itself
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => arg.push(chars.next()?),
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '\\' => current.get_or_insert_with(String::new).push(chars.next()?),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
     }
-    
-    let parsed = RE.replace_all(exec, "");
-    let parsed = parsed.replace("%i", &format!("--icon {}", icon));
-    let parsed = parsed.trim();
+    args.extend(current);
+
+    let mut argv = Vec::new();
+    for arg in args {
+        if arg == "%i" {
+            if !icon.is_empty() {
+                argv.push("--icon".to_string());
+                argv.push(escape_field_codes(icon));
+            }
+            continue;
+        }
 
-    // Strip surrounding quotes if present
-    let parsed = parsed.strip_prefix('"').unwrap_or(parsed);
-    let parsed = parsed.strip_suffix('"').unwrap_or(parsed);
+        let mut expanded = String::new();
+        let mut chars = arg.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some(code @ ('%' | 'f' | 'F' | 'u' | 'U')) => {
+                    expanded.push('%');
+                    expanded.push(code);
+                }
+                Some('c') => expanded.push_str(&escape_field_codes(name)),
+                Some('k') => expanded.push_str(&escape_field_codes(&path.to_string_lossy())),
+                // Deprecated and unknown codes are dropped
+                _ => {}
+            }
+        }
+        // Only arguments that were nothing but dropped codes disappear, an
+        // explicit "" is kept
+        if !expanded.is_empty() || arg.is_empty() {
+            argv.push(expanded);
+        }
+    }
 
-    parsed.to_string()
+    (!argv.is_empty()).then_some(argv)
 }
 
 impl XdgDesktopFinder {
-    pub fn new() -> Self {
-        XdgDesktopFinder
+    /// With `check`, desktop files are also checked for mistakes, as
+    /// `check_desktop_files` does, and the problems are returned as warnings.
+    pub fn new(config: DesktopConfig, terminal: Option<Vec<String>>, nested_actions: bool, check: bool) -> Self {
+        XdgDesktopFinder { config, terminal, nested_actions, check, diagnostics: Mutex::new(Vec::new()) }
+    }
+
+    fn report(&self, diagnostics: Vec<Diagnostic>) {
+        if !diagnostics.is_empty() {
+            self.diagnostics.lock().unwrap().extend(diagnostics);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn hidden_entries_name_the_key_that_hides_them() {
+        let reason = hidden_reason(&entries(&[("NoDisplay", "true"), ("TryExec", "no-such-binary")]), &[]).unwrap();
+        assert_eq!(reason, HiddenReason::NoDisplay);
+        assert_eq!(reason.key(), "NoDisplay");
+        assert_eq!(reason.to_string(), "NoDisplay=true");
+
+        let reason = hidden_reason(&entries(&[("TryExec", "no-such-binary")]), &[]).unwrap();
+        assert_eq!(reason.key(), "TryExec");
+        assert_eq!(reason.to_string(), "TryExec=no-such-binary");
+    }
+
+    #[test]
+    fn entries_hidden_from_menus_are_still_installed() {
+        assert!(is_installed(&entries(&[("NoDisplay", "true"), ("TryExec", "sh")])));
+        assert!(!is_installed(&entries(&[("NoDisplay", "true"), ("TryExec", "no-such-binary")])));
+        assert!(!is_installed(&entries(&[("Hidden", "true")])));
+    }
+
+    #[test]
+    fn handlers_can_be_hidden_from_menus() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/desktop");
+        let id = "userapp-mpv-X1Y2Z3.desktop";
+        let items = handler_items(id, &fixtures.join(id), None, false);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id.as_deref(), Some(id));
+        assert_eq!(items[0].mime_types, ["video/mp4"]);
+        assert!(!items[0].description.starts_with("Hidden"));
+
+        assert!(handler_items("deleted.desktop", &fixtures.join("deleted.desktop"), None, false).is_empty());
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn locales_fall_back_from_the_most_specific() {
+        assert_eq!(message_locales("sr_RS.UTF-8@latin"), ["sr_RS@latin", "sr_RS", "sr@latin", "sr"]);
+        assert_eq!(message_locales("de_DE.UTF-8"), ["de_DE", "de"]);
+        assert_eq!(message_locales("ca@valencia"), ["ca@valencia", "ca"]);
+        assert_eq!(message_locales("fr"), ["fr"]);
+        assert!(message_locales("C.UTF-8").is_empty());
+        assert!(message_locales("POSIX").is_empty());
+        assert!(message_locales("").is_empty());
+    }
+
+    #[test]
+    fn localized_keys_use_the_first_matching_locale() {
+        let locales = message_locales("sr_RS.UTF-8@latin");
+        let mut entries = entries(&[
+            ("Name", "Name"),
+            ("Name[sr]", "sr"),
+            ("Name[sr@latin]", "sr@latin"),
+            ("Name[sr_RS]", "sr_RS"),
+            ("Name[sr_RS@latin]", "sr_RS@latin"),
+        ]);
+        for expected in ["sr_RS@latin", "sr_RS", "sr@latin", "sr", "Name"] {
+            assert_eq!(localized_in(&entries, "Name", &locales).unwrap(), expected);
+            entries.remove(&format!("Name[{}]", expected));
+        }
+        assert_eq!(localized_in(&entries, "Comment", &locales), None);
+    }
+
+    #[test]
+    fn untranslated_keywords_and_names_are_searchable() {
+        let entries = entries(&[
+            ("Name", "Files"),
+            ("Name[de]", "Dateien"),
+            ("Keywords", "folder;manager;"),
+            ("Keywords[de]", "Ordner;manager;"),
+        ]);
+        assert_eq!(search_keywords(&entries, &strings(&["de"])), ["Ordner", "manager", "folder", "Files"]);
+        assert_eq!(search_keywords(&entries, &[]), ["folder", "manager"]);
+    }
+
+    #[test]
+    fn entries_can_be_limited_to_desktops() {
+        let only_kde = entries(&[("OnlyShowIn", "KDE;")]);
+        assert_eq!(hidden_reason(&only_kde, &strings(&["KDE"])), None);
+        assert_eq!(hidden_reason(&only_kde, &strings(&["ubuntu", "GNOME"])), Some(HiddenReason::OnlyShowIn("KDE;".to_string())));
+        assert_eq!(hidden_reason(&only_kde, &[]), Some(HiddenReason::OnlyShowIn("KDE;".to_string())));
+
+        let not_gnome = entries(&[("NotShowIn", "GNOME;Unity;")]);
+        assert_eq!(hidden_reason(&not_gnome, &strings(&["KDE"])), None);
+        assert_eq!(hidden_reason(&not_gnome, &strings(&["ubuntu", "GNOME"])), Some(HiddenReason::NotShowIn("GNOME;Unity;".to_string())));
+        assert_eq!(hidden_reason(&not_gnome, &[]), None);
+    }
+
+    #[test]
+    fn entries_need_their_try_exec_binary() {
+        assert_eq!(hidden_reason(&entries(&[("TryExec", "sh")]), &[]), None);
+        assert_eq!(hidden_reason(&entries(&[("TryExec", "/bin/sh")]), &[]), None);
+        assert_eq!(hidden_reason(&entries(&[("TryExec", "/no/such/binary")]), &[]), Some(HiddenReason::TryExec("/no/such/binary".to_string())));
+    }
+
+    #[test]
+    fn user_entries_shadow_system_entries() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/desktop");
+        let files = find_desktop_files(&[fixtures.join("data-home"), fixtures.join("data-dirs")]);
+        let ids: Vec<&str> = files.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["org.example.Editor.desktop", "kde-org.example.Settings.desktop", "org.example.Viewer.desktop"]);
+
+        // The user's deleted copy hides the system one
+        let (id, path) = &files[0];
+        assert!(path.starts_with(fixtures.join("data-home")));
+        let (entries, _) = read_desktop_file(path).unwrap();
+        assert_eq!(hidden_reason(&entries, &[]), Some(HiddenReason::Hidden));
+        assert!(handler_items(id, path, None, false).is_empty());
+    }
+
+    fn parse(exec: &str) -> Option<Vec<String>> {
+        parse_exec(exec, "mpv", "100% Player", Path::new("/apps/mpv.desktop"))
+    }
+
+    #[test]
+    fn exec_values_are_split_into_arguments() {
+        let cases: &[(&str, &[&str])] = &[
+            ("mpv --player-operation-mode=pseudo-gui -- %U", &["mpv", "--player-operation-mode=pseudo-gui", "--", "%U"]),
+            (r#"app "two words"  three"#, &["app", "two words", "three"]),
+            (r#"app --title """#, &["app", "--title", ""]),
+            (r#"app pre"quoted"post"#, &["app", "prequotedpost"]),
+            // \s is a space, which still separates unquoted arguments
+            (r"app a\sb", &["app", "a", "b"]),
+            // The string escapes are undone before the quoting rules
+            (r#"sh -c "echo \\"hi\\"""#, &["sh", "-c", r#"echo "hi""#]),
+            (r#"sh -c "echo \\`date\\` \\$HOME""#, &["sh", "-c", "echo `date` $HOME"]),
+            (r#"printf "a\\\\b""#, &["printf", r"a\b"]),
+            // Codes stay escaped for expand_field_codes
+            ("printf 100%%", &["printf", "100%%"]),
+            ("app --name %c --desktop-file %k", &["app", "--name", "100%% Player", "--desktop-file", "/apps/mpv.desktop"]),
+            ("app %i %F", &["app", "--icon", "mpv", "%F"]),
+            ("app --file=%f --urls=%U", &["app", "--file=%f", "--urls=%U"]),
+            // Deprecated codes are dropped
+            ("app %d %m file", &["app", "file"]),
+        ];
+        for (exec, argv) in cases {
+            assert_eq!(parse(exec), Some(strings(argv)), "{}", exec);
+        }
+    }
+
+    #[test]
+    fn codes_are_expanded_when_launching() {
+        let argv = parse("app --file=%f --title=%%s %F").unwrap();
+        let targets = ["/tmp/a b.txt".to_string(), "/tmp/c.txt".to_string()];
+        assert_eq!(expand_field_codes(&argv, &targets), ["app", "--file=/tmp/a b.txt", "--title=%s", "/tmp/a b.txt", "/tmp/c.txt"]);
+        assert_eq!(expand_field_codes(&argv, &[]), ["app", "--file=", "--title=%s"]);
+    }
+
+    #[test]
+    fn malformed_exec_values_are_rejected() {
+        assert_eq!(parse(r#"app "unterminated"#), None);
+        assert_eq!(parse(r#"app "escaped quote\\""#), None);
+        assert_eq!(parse(r"app trailing\\"), None);
+        assert_eq!(parse("  "), None);
+    }
+
+    #[test]
+    fn the_icon_code_is_dropped_without_an_icon() {
+        assert_eq!(parse_exec("app %i", "", "App", Path::new("/apps/app.desktop")).unwrap(), ["app"]);
+    }
+
+    // The diagnostics about the file's contents, as "line: severity: problem"
+    fn problems(diagnostics: Vec<Diagnostic>) -> Vec<String> {
+        diagnostics.iter()
+            .filter(|diagnostic| diagnostic.line > 0)
+            .map(|diagnostic| format!("{}: {:?}: {}", diagnostic.line, diagnostic.severity, diagnostic.problem))
+            .collect()
+    }
+
+    #[test]
+    fn check_desktop_reports_problems_by_line() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/desktop/check/broken.desktop");
+        let diagnostics = check_desktop_file(&path);
+        assert_eq!(problems(diagnostics.clone()), [
+            r#"4: Error: Exec=broken "unterminated can't be parsed, so the entry is skipped"#,
+            "5: Warning: Terminal=yes is neither true nor false",
+            "6: Info: the entry is hidden by NoDisplay=true, --all lists it anyway",
+            "7: Warning: action missing has no [Desktop Action missing] group",
+            "9: Warning: [Desktop Action new] has no Name, so the action is skipped",
+            "12: Warning: [Desktop Action unlisted] is not listed in Actions, so it is skipped",
+        ]);
+        let first = diagnostics.iter().find(|diagnostic| diagnostic.line == 4).unwrap();
+        assert_eq!(first.to_string(), format!(r#"{}:4: error: Exec=broken "unterminated can't be parsed, so the entry is skipped"#, path.display()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn check_desktop_reports_missing_keys() {
+        let (file, diagnostics) = DesktopFile::parse(Path::new("entry.txt"), b"[Desktop Entry]\nComment=No name\n");
+        assert_eq!(problems(check_desktop_entry(&file, diagnostics)), [
+            "1: Warning: Type is missing, so the entry is treated as an application",
+            "1: Error: Name is missing, so the entry is skipped",
+            "1: Error: Exec is missing, so the entry is skipped",
+        ]);
+
+        let (file, diagnostics) = DesktopFile::parse(Path::new("entry.txt"), b"[Desktop Entry]\nType=Link\nName=Docs\n");
+        assert_eq!(problems(check_desktop_entry(&file, diagnostics)), ["1: Error: URL is missing, so the link is skipped"]);
+
+        let (file, diagnostics) = DesktopFile::parse(Path::new("entry.txt"), b"[Desktop Entry]\nType=Service\nName=Daemon\n");
+        assert_eq!(problems(check_desktop_entry(&file, diagnostics)), ["2: Error: Type=Service is not Application, Link or Directory, so the entry is skipped"]);
+
+        let missing = check_desktop_file(Path::new("/no/such/file.desktop"));
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].problem.starts_with("can't be read: "));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::FuzzydError;
+use crate::icons::IconLookup;
+use base64::Engine;
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+// Cell size in pixels when the terminal doesn't report one
+const DEFAULT_CELL_SIZE: (u32, u32) = (8, 16);
+
+/// How item icons are drawn.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Graphics {
+    Kitty,
+    Sixel,
+    Glyphs,
+}
+
+impl Graphics {
+    /// Picks the mode from the `icons` setting, detecting what the terminal
+    /// supports unless it names one.
+    pub fn detect(setting: Option<&str>) -> Self {
+        match setting {
+            Some("kitty") => Graphics::Kitty,
+            Some("sixel") => Graphics::Sixel,
+            Some("glyphs") => Graphics::Glyphs,
+            _ => {
+                let var = |name: &str| env::var(name).unwrap_or_default();
+                let term = var("TERM");
+                let program = var("TERM_PROGRAM");
+                if env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || program == "ghostty" {
+                    Graphics::Kitty
+                } else if term.starts_with("foot") || term.starts_with("mlterm") || program == "WezTerm" || program == "contour" {
+                    Graphics::Sixel
+                } else {
+                    Graphics::Glyphs
+                }
+            }
+        }
+    }
+}
+
+/// Draws application icons in the two cells before an item, in place of
+/// its glyph.
+pub struct IconRenderer {
+    graphics: Graphics,
+    lookup: Option<IconLookup>,
+    cell_size: (u32, u32),
+    // Kitty keeps images it was sent, so each file is only sent once
+    kitty_ids: HashMap<PathBuf, u32>,
+    sixels: HashMap<PathBuf, Option<String>>,
+}
+
+impl IconRenderer {
+    pub fn new(graphics: Graphics, theme: &str, tty: &File) -> Self {
+        // Only PNG files can be drawn
+        let lookup = (graphics != Graphics::Glyphs).then(|| IconLookup::new(theme, &["png"]));
+        IconRenderer {
+            graphics,
+            lookup,
+            cell_size: cell_size(tty).unwrap_or(DEFAULT_CELL_SIZE),
+            kitty_ids: HashMap::new(),
+            sixels: HashMap::new(),
+        }
+    }
+
+    /// Clears the icons of the previous frame, which clearing the screen
+    /// leaves behind in kitty.
+    pub fn clear<W: Write>(&self, screen: &mut W) -> Result<(), FuzzydError> {
+        if self.graphics == Graphics::Kitty {
+            write!(screen, "\x1b_Ga=d,d=a,q=2\x1b\\")?;
+        }
+        Ok(())
+    }
+
+    /// Draws the icon at the cursor, returning false when it can't be
+    /// drawn and the glyph should be shown instead.
+    pub fn draw<W: Write>(&mut self, screen: &mut W, icon: Option<&str>) -> Result<bool, FuzzydError> {
+        let (lookup, icon) = match (self.lookup.as_mut(), icon) {
+            (Some(lookup), Some(icon)) => (lookup, icon),
+            _ => return Ok(false),
+        };
+        let path = match lookup.find(icon, self.cell_size.1, 1) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+
+        match self.graphics {
+            Graphics::Kitty => {
+                let next_id = self.kitty_ids.len() as u32 + 1;
+                let id = match self.kitty_ids.get(&path) {
+                    Some(id) => *id,
+                    None => {
+                        let encoded = base64::engine::general_purpose::STANDARD.encode(path.as_os_str().as_encoded_bytes());
+                        write!(screen, "\x1b_Ga=t,t=f,f=100,i={},q=2;{}\x1b\\", next_id, encoded)?;
+                        self.kitty_ids.insert(path, next_id);
+                        next_id
+                    }
+                };
+                // C=1 keeps the cursor where it is
+                write!(screen, "\x1b_Ga=p,i={},c=2,r=1,C=1,q=2\x1b\\", id)?;
+                Ok(true)
+            }
+            Graphics::Sixel => {
+                let cell_size = self.cell_size;
+                let sixel = self.sixels.entry(path)
+                    .or_insert_with_key(|path| encode_sixel(path, cell_size));
+                match sixel {
+                    Some(sixel) => {
+                        write!(screen, "{}", sixel)?;
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                }
+            }
+            Graphics::Glyphs => Ok(false),
+        }
+    }
+}
+
+fn cell_size(tty: &File) -> Option<(u32, u32)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) } != 0 {
+        return None;
+    }
+    if size.ws_col == 0 || size.ws_row == 0 || size.ws_xpixel == 0 || size.ws_ypixel == 0 {
+        return None;
+    }
+    Some((
+        size.ws_xpixel as u32 / size.ws_col as u32,
+        size.ws_ypixel as u32 / size.ws_row as u32,
+    ))
+}
+
+// Scales the PNG to a square that fits one row, since sixels are drawn in
+// bands of 6 pixels and anything taller would spill into the next row
+fn encode_sixel(path: &Path, (cell_width, cell_height): (u32, u32)) -> Option<String> {
+    let (width, height, pixels) = decode_png(path)?;
+    let side = (cell_height / 6 * 6).min(cell_width * 2).max(6);
+
+    // A 6x6x6 color cube, with mostly transparent pixels left out
+    let level = |value: u8| (value as u32 * 5 + 127) / 255;
+    let mut indexes = vec![None; (side * side) as usize];
+    for y in 0..side {
+        for x in 0..side {
+            let offset = (((y * height / side) * width + x * width / side) * 4) as usize;
+            let pixel = &pixels[offset..offset + 4];
+            if pixel[3] >= 128 {
+                let index = level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]);
+                indexes[(y * side + x) as usize] = Some(index);
+            }
+        }
+    }
+
+    let mut sixel = format!("\x1bP0;1;0q\"1;1;{};{}", side, side);
+    let mut used: Vec<u32> = indexes.iter().flatten().copied().collect();
+    used.sort_unstable();
+    used.dedup();
+    for index in &used {
+        let percent = |value: u32| value * 100 / 5;
+        sixel.push_str(&format!("#{};2;{};{};{}", index, percent(index / 36), percent(index / 6 % 6), percent(index % 6)));
+    }
+
+    for band in 0..side / 6 {
+        if band > 0 {
+            sixel.push('-');
+        }
+        let mut first = true;
+        for index in &used {
+            let columns: Vec<u8> = (0..side)
+                .map(|x| {
+                    (0..6).fold(0, |bits, row| {
+                        let y = band * 6 + row;
+                        if indexes[(y * side + x) as usize] == Some(*index) { bits | 1 << row } else { bits }
+                    })
+                })
+                .collect();
+            if columns.iter().all(|bits| *bits == 0) {
+                continue;
+            }
+            if !first {
+                sixel.push('$');
+            }
+            first = false;
+            sixel.push_str(&format!("#{}", index));
+            push_runs(&mut sixel, &columns);
+        }
+    }
+    sixel.push_str("\x1b\\");
+    Some(sixel)
+}
+
+// Repeated sixels are run-length encoded as !count
+fn push_runs(sixel: &mut String, columns: &[u8]) {
+    let mut start = 0;
+    while start < columns.len() {
+        let bits = columns[start];
+        let run = columns[start..].iter().take_while(|other| **other == bits).count();
+        let character = (bits + 63) as char;
+        if run > 3 {
+            sixel.push_str(&format!("!{}{}", run, character));
+        } else {
+            sixel.extend(std::iter::repeat_n(character, run));
+        }
+        start += run;
+    }
+}
+
+// Returns the width, height and RGBA pixels of a PNG file
+fn decode_png(path: &Path) -> Option<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(File::open(path).ok()?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().ok()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).ok()?;
+    let data = &buffer[..frame.buffer_size()];
+
+    let pixels = match frame.color_type {
+        png::ColorType::Rgba => data.to_vec(),
+        png::ColorType::Rgb => data.chunks(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks(2).flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]]).collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+        png::ColorType::Indexed => return None,
+    };
+    if frame.width == 0 || frame.height == 0 {
+        return None;
+    }
+    Some((frame.width, frame.height, pixels))
+}
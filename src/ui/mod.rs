@@ -1,3 +1,4 @@
 mod terminal;
+mod graphics;
 
-pub use terminal::TerminalUI;
\ No newline at end of file
+pub use terminal::TerminalUI;
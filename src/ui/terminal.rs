@@ -1,9 +1,12 @@
-use crate::fuzzy::{FuzzyFinder, FuzzyItem};
+use crate::fuzzy::{FuzzyFinder, FuzzyItem, ItemAction};
 use crate::config::UIConfig;
 use crate::error::FuzzydError;
+use crate::menu::Menu;
+use crate::icons;
+use super::graphics::{Graphics, IconRenderer};
 use unicode_width::UnicodeWidthStr;
 use unicode_width::UnicodeWidthChar;
-use std::io::{Write, stdout};
+use std::io::Write;
 use termion::input::TermRead;
 use termion::event::Key;
 use termion::{cursor, clear, color, style};
@@ -13,36 +16,99 @@ use termion::raw::IntoRawMode;
 pub struct TerminalUI {
     config: UIConfig,
     debug: bool,
+    menu: Option<Menu>,
 }
 
 impl TerminalUI {
     pub fn new(config: UIConfig, debug: bool) -> Self {
-        TerminalUI { config, debug }
+        TerminalUI { config, debug, menu: None }
+    }
+
+    /// Sets the menu browsed while the query is empty.
+    pub fn set_menu(&mut self, menu: Option<Menu>) {
+        self.menu = menu;
     }
 
     pub fn run(&mut self, finder: &mut FuzzyFinder) -> Result<Option<FuzzyItem>, FuzzydError> {
-        let mut screen = stdout().into_raw_mode().unwrap().into_alternate_screen().unwrap();
+        // Talk to the terminal directly, so stdout stays free for --print
+        let tty = termion::get_tty()?;
+        let mut keys = tty.try_clone()?.keys();
+        let mut screen = tty.try_clone()?.into_raw_mode()?.into_alternate_screen()?;
         let mut query = String::new();
         let mut cursor_pos = 0;
         let mut selected = 0;
         let mut history: Vec<String> = Vec::new();
         let mut history_index = 0;
+        let mut menu_path: Vec<usize> = Vec::new();
+        let mut expanded: Option<String> = None;
+        let theme = self.config.icon_theme.clone()
+            .or_else(icons::default_theme)
+            .unwrap_or_else(|| "hicolor".to_string());
+        let mut icons = IconRenderer::new(Graphics::detect(self.config.icons.as_deref()), &theme, &tty);
 
         loop {
-            let matches = finder.find(&query);
-            self.draw_screen(&mut screen, &query, cursor_pos, &matches, selected)?;
+            // An empty query browses the menu: submenus first, then entries
+            let menu = self.menu.as_ref().filter(|_| query.is_empty())
+                .map(|menu| menu_path.iter().fold(menu, |menu, &index| &menu.submenus[index]));
+            let browsed = menu.map(menu_items).unwrap_or_default();
+            let (found, provided) = if menu.is_some() {
+                (browsed.iter().map(|item| (0.0, item)).collect(), Vec::new())
+            } else {
+                let found = finder.find(&query);
+                let provided = finder.provide(&query, !found.is_empty());
+                (found, provided)
+            };
+
+            // Provided items are computed for this exact query, so they go first
+            let mut matches: Vec<(f64, &FuzzyItem)> = provided.iter().map(|item| (f64::INFINITY, item)).collect();
+            matches.extend(found);
 
-            match std::io::stdin().keys().next().unwrap()? {
+            // The expanded item's actions follow it
+            if let Some(index) = matches.iter().position(|(_, item)| item.id.is_some() && item.id == expanded) {
+                let (score, item) = matches[index];
+                matches.splice(index + 1..index + 1, item.actions.iter().map(|action| (score, action)));
+            }
+            let size = termion::terminal_size_fd(&tty)?;
+            self.draw_screen(&mut screen, &mut icons, size, (&query, cursor_pos), &matches, selected)?;
+
+            match keys.next().unwrap()? {
+                Key::Char('\n') if matches.get(selected).is_some_and(|(_, item)| matches!(item.action, ItemAction::Browse(_))) => {
+                    if let ItemAction::Browse(index) = matches[selected].1.action {
+                        menu_path.push(index);
+                        selected = 0;
+                    }
+                },
                 Key::Char('\n') if !matches.is_empty() => {
                     let selected_item = matches[selected].1.clone();
                     history.push(query.clone());
                     return Ok(Some(selected_item));
                 },
+                // Open the selected item's file or URL with another application
+                Key::Ctrl('o') if matches.get(selected).is_some_and(|(_, item)| item.target.is_some()) => {
+                    let mut selected_item = matches[selected].1.clone();
+                    selected_item.action = ItemAction::OpenWith(selected_item.target.clone().unwrap_or_default());
+                    return Ok(Some(selected_item));
+                },
+                // Expand or collapse the selected item's actions
+                Key::Char('\t') => {
+                    if let Some((_, item)) = matches.get(selected) {
+                        if expanded.is_some() && expanded == item.id {
+                            expanded = None;
+                        } else if !item.actions.is_empty() {
+                            expanded = item.id.clone();
+                        }
+                    }
+                },
                 Key::Char(c) => {
                     query.insert(cursor_pos, c);
                     cursor_pos += 1;
                     selected = 0;
                 },
+                // Go back up the menu
+                Key::Backspace | Key::Esc if query.is_empty() && !menu_path.is_empty() => {
+                    menu_path.pop();
+                    selected = 0;
+                },
                 Key::Backspace if cursor_pos > 0 => {
                     query.remove(cursor_pos - 1);
                     cursor_pos -= 1;
@@ -70,13 +136,11 @@ impl TerminalUI {
                     }
                     selected = 0;
                 },
-                Key::Ctrl('p') if !history.is_empty() => {
-                    if history_index < history.len() {
-                        history_index += 1;
-                        query = history[history.len() - history_index].clone();
-                        cursor_pos = query.len();
-                        selected = 0;
-                    }
+                Key::Ctrl('p') if history_index < history.len() => {
+                    history_index += 1;
+                    query = history[history.len() - history_index].clone();
+                    cursor_pos = query.len();
+                    selected = 0;
                 },
                 Key::Ctrl('n') if history_index > 0 => {
                     history_index -= 1;
@@ -105,10 +169,45 @@ impl TerminalUI {
         }
     }
 
-    fn draw_screen<W: Write>(&self, screen: &mut W, query: &str, cursor_pos: usize, matches: &[(f64, &FuzzyItem)], selected: usize) -> Result<(), FuzzydError> {
+    /// Asks for a single line of text, returning `None` if Esc is pressed.
+    pub fn prompt(&mut self, label: &str) -> Result<Option<String>, FuzzydError> {
+        let tty = termion::get_tty()?;
+        let mut keys = tty.try_clone()?.keys();
+        let mut screen = tty.try_clone()?.into_raw_mode()?.into_alternate_screen()?;
+        let mut input = String::new();
+
+        loop {
+            let (width, height) = termion::terminal_size_fd(&tty)?;
+            let label = truncate_str(&format!("{}:", label), width as usize / 2);
+            write!(screen, "{}{}{}{}{}{} {}",
+                clear::All,
+                cursor::Goto(1, height),
+                color::Fg(color::White),
+                style::Bold,
+                label,
+                style::Reset,
+                input
+            )?;
+            screen.flush()?;
+
+            match keys.next().unwrap()? {
+                Key::Char('\n') => return Ok(Some(input)),
+                Key::Char(c) => input.push(c),
+                Key::Backspace => {
+                    input.pop();
+                },
+                Key::Ctrl('u') => input.clear(),
+                Key::Esc => return Ok(None),
+                Key::Ctrl('c') => return Err(FuzzydError::UserInterrupt),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_screen<W: Write>(&self, screen: &mut W, icons: &mut IconRenderer, (width, height): (u16, u16), (query, cursor_pos): (&str, usize), matches: &[(f64, &FuzzyItem)], selected: usize) -> Result<(), FuzzydError> {
         write!(screen, "{}", clear::All)?;
+        icons.clear(screen)?;
 
-        let (width, height) = termion::terminal_size()?;
         let max_items = height.saturating_sub(if self.debug { 4 } else { 3 }) as usize; // Adjust based on debug mode
 
         // Display selected item details at the top
@@ -153,11 +252,19 @@ impl TerminalUI {
             let y = (height - if self.debug { 1 } else { 0 } - (i - start_index) as u16).saturating_sub(1);
             let is_selected = i == selected;
             let display = highlight_matches(&item.display, query, width as usize - 15, is_selected);
+            // The application icon takes the place of the glyph when it can
+            // be drawn, and the text skips over it
+            write!(screen, "{}", cursor::Goto(3, y))?;
+            let icon = if icons.draw(screen, item.icon_name.as_deref())? {
+                cursor::Goto(5, y).to_string()
+            } else {
+                item.icon.clone()
+            };
             if is_selected {
                 write!(screen, "{}{}> {} {}", 
                     cursor::Goto(1, y),
                     color::Fg(color::Green),
-                    item.icon,
+                    icon,
                     display
                 )?;
                 if self.debug {
@@ -165,7 +272,7 @@ impl TerminalUI {
                 }
                 write!(screen, "{}", color::Fg(color::Reset))?;
             } else {
-                write!(screen, "{}  {} {}", cursor::Goto(1, y), item.icon, display)?;
+                write!(screen, "{}  {} {}", cursor::Goto(1, y), icon, display)?;
                 if self.debug {
                     write!(screen, " ({:.1}) ({})", score, item.source_path)?;
                 }
@@ -205,6 +312,28 @@ impl TerminalUI {
     }
 }
 
+// Submenus are shown as items that open them
+fn menu_items(menu: &Menu) -> Vec<FuzzyItem> {
+    let submenus = menu.submenus.iter()
+        .enumerate()
+        .map(|(index, submenu)| FuzzyItem {
+            display: submenu.name.clone(),
+            description: submenu.comment.clone().unwrap_or_else(|| match count_items(submenu) {
+                1 => "1 application".to_string(),
+                count => format!("{} applications", count),
+            }),
+            icon: " ".to_string(),
+            icon_name: submenu.icon.clone(),
+            action: ItemAction::Browse(index),
+            ..Default::default()
+        });
+    submenus.chain(menu.items.iter().cloned()).collect()
+}
+
+fn count_items(menu: &Menu) -> usize {
+    menu.items.len() + menu.submenus.iter().map(count_items).sum::<usize>()
+}
+
 fn highlight_matches(text: &str, query: &str, max_width: usize, is_selected: bool) -> String {
     let mut result = String::new();
     let lowercase_text = text.to_lowercase();
@@ -228,19 +357,19 @@ fn highlight_matches(text: &str, query: &str, max_width: usize, is_selected: boo
                 if is_selected {
                     result.push_str(&format!("{}{}", style::Bold, color::Fg(color::Green)));
                 } else {
-                    result.push_str(&style::Bold.to_string());
+                    result.push_str(style::Bold.as_ref());
                 }
                 is_highlighting = true;
             }
         } else if is_highlighting {
-            result.push_str(&style::Reset.to_string());
+            result.push_str(style::Reset.as_ref());
             is_highlighting = false;
         }
         result.push(c);
     }
 
     if is_highlighting {
-        result.push_str(&style::Reset.to_string());
+        result.push_str(style::Reset.as_ref());
     }
 
     truncate_str(&result, max_width)